@@ -1,27 +1,31 @@
 use criterion::{criterion_group, criterion_main, Criterion};
-use helpers::{grid::Grid, light::{LightState, TrafficLight}, variables::CAR_ID_COUNTER, vehicle::Vehicle};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use engine::helpers::{config::SimulationConfig, grid::Grid, light::{LightState, TrafficLight}, spawn::Arrival, vehicle::Vehicle};
+use engine::Simulation;
 use tokio::runtime::Runtime;
-use std::sync::atomic::{Ordering};
-
-mod helpers;
-
-// Reset global counters to ensure consistent benchmarks
-fn reset_globals() {
-    CAR_ID_COUNTER.store(0, Ordering::SeqCst);
-}
+use std::sync::Arc;
 
 // Benchmark vehicle generation throughput
 fn benchmark_vehicle_generation(c: &mut Criterion) {
     let mut group = c.benchmark_group("Vehicle Generation");
-    reset_globals();
 
     group.bench_function("Generate 100 Vehicles", |b| {
         let rt = Runtime::new().unwrap();
+        let config = SimulationConfig::default();
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let grid = Grid::generate_grid(Grid::new(), 3, 3, &config.lights, &config.roads, &mut rng);
         b.iter(|| {
             rt.block_on(async {
                 let mut handles = vec![];
-                for _ in 0..100 {
-                    handles.push(tokio::spawn(Vehicle::generate_vehicle()));
+                for id in 0..100 {
+                    handles.push(tokio::spawn(Vehicle::generate_vehicle(
+                        id,
+                        Arc::clone(&grid.network),
+                        config.vehicles.clone(),
+                        Arrival::default(),
+                        ChaCha8Rng::from_rng(&mut rng),
+                    )));
                 }
                 for handle in handles {
                     handle.await.unwrap();
@@ -38,11 +42,13 @@ fn benchmark_traffic_light_updates(c: &mut Criterion) {
     
     group.bench_function("Update 9 Traffic Lights", |b| {
         let rt = Runtime::new().unwrap();
+        let config = SimulationConfig::default();
         let mut lights = vec![];
         for _ in 0..9 {
             lights.push(TrafficLight::new(
                 LightState::Green,
                 (0, 0),
+                &config.lights,
             ));
         }
 
@@ -58,26 +64,46 @@ fn benchmark_traffic_light_updates(c: &mut Criterion) {
 // Benchmark grid update cycle (vehicles + lights)
 fn benchmark_grid_updates(c: &mut Criterion) {
     let mut group = c.benchmark_group("Grid Update Cycle");
-    reset_globals();
 
     group.bench_function("Full Grid Update (120ms Interval)", |b| {
         let rt = Runtime::new().unwrap();
-        let mut grid = Grid::generate_grid(Grid::new(), 3, 3);
+        let config = SimulationConfig::default();
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut grid = Grid::generate_grid(Grid::new(), 3, 3, &config.lights, &config.roads, &mut rng);
         
         b.iter(|| {
             rt.block_on(async {
-                grid.update_vehicles().await;
-                grid.update_traffic_lights(0.3).await; // Simulate 300ms interval
+                // Simulate 300ms interval
+                grid.update_vehicles(0.3).await;
+                grid.update_traffic_lights(0.3).await;
             });
         });
     });
     group.finish();
 }
 
+// Benchmark whole simulation ticks (spawning, lights, vehicles)
+fn benchmark_simulation_steps(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Simulation Step");
+
+    group.bench_function("Step a seeded 3x3 simulation", |b| {
+        let rt = Runtime::new().unwrap();
+        let mut config = SimulationConfig::default();
+        config.simulation.seed = Some(0);
+        let mut simulation = Simulation::new(config).unwrap();
+
+        b.iter(|| {
+            rt.block_on(simulation.step());
+        });
+    });
+    group.finish();
+}
+
 criterion_group!(
     benches,
     benchmark_vehicle_generation,
     benchmark_traffic_light_updates,
-    benchmark_grid_updates
+    benchmark_grid_updates,
+    benchmark_simulation_steps
 );
 criterion_main!(benches);
\ No newline at end of file
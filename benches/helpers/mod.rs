@@ -1,7 +0,0 @@
-pub mod variables;
-pub mod analyzer;
-pub mod grid;
-pub mod light;
-pub mod message;
-pub mod point;
-pub mod vehicle;
\ No newline at end of file
@@ -0,0 +1,351 @@
+// builder.rs
+use crate::event_log::{EventLog, EventLogError};
+use crate::helpers::analyzer::Analyzer;
+use crate::helpers::boundary::Boundary;
+use crate::helpers::config::{ConfigError, LightConfig, RoadConfig, SimulationConfig, VehicleProfiles};
+use crate::helpers::demand::{DemandError, DemandFile};
+use crate::helpers::grid::Grid;
+use crate::helpers::light::SignalPlan;
+use crate::helpers::network_file::NetworkError;
+use crate::helpers::spawn::SpawnPolicy;
+use crate::render::Renderer;
+use crate::scenario::Scenario;
+use crate::snapshot::{Snapshot, SnapshotError};
+use crate::Simulation;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+// Set up a simulation step by step, starting from the default config:
+//
+//     let simulation = SimulationBuilder::new()
+//         .grid_size(4, 6)
+//         .seed(42)
+//         .signal_plan((10, 10), plan)
+//         .renderer(TerminalRenderer)
+//         .build()?;
+//
+// Nothing is checked before build(), which reports the first problem.
+#[derive(Default)]
+pub struct SimulationBuilder {
+    config: SimulationConfig,
+    // Timing of single lights, by position
+    signal_plans: Vec<((i32, i32), SignalPlan)>,
+    analyzers: Vec<Box<dyn Analyzer>>,
+    renderers: Vec<Box<dyn Renderer>>,
+    // Snapshot file to carry on from instead of starting a new run
+    resume_from: Option<String>,
+    // JSON Lines file to log every tick to
+    event_log: Option<String>,
+    scenario: Option<Scenario>,
+}
+
+#[derive(Debug)]
+pub enum BuildError {
+    // The settings are out of range
+    Config(ConfigError),
+    // The network or OSM file could not be loaded
+    Network(NetworkError),
+    // A signal plan for a position without a traffic light
+    NoLightAt((i32, i32)),
+    // A signal plan with a zero or negative duration
+    InvalidSignalPlan { position: (i32, i32), message: String },
+    // The snapshot to resume from could not be loaded
+    Snapshot(SnapshotError),
+    // The event log could not be created
+    EventLog(EventLogError),
+    // The scenario does not fit its network
+    Scenario(String),
+    // The OD matrix could not be loaded or does not fit the network
+    Demand(DemandError),
+}
+
+impl SimulationBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Start from a loaded config file
+    pub fn from_config(config: SimulationConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    // Generate a height x width lattice of points
+    pub fn grid_size(mut self, height: i32, width: i32) -> Self {
+        self.config.grid.height = height;
+        self.config.grid.width = width;
+        self.config.grid.network_file = None;
+        self.config.grid.osm_file = None;
+        self
+    }
+
+    // Load a JSON road network instead
+    pub fn network_file(mut self, path: &str) -> Self {
+        self.config.grid.network_file = Some(path.to_string());
+        self.config.grid.osm_file = None;
+        self
+    }
+
+    // Load an OpenStreetMap XML extract instead
+    pub fn osm_file(mut self, path: &str, meters_per_unit: f32) -> Self {
+        self.config.grid.osm_file = Some(path.to_string());
+        self.config.grid.network_file = None;
+        self.config.grid.meters_per_unit = meters_per_unit;
+        self
+    }
+
+    // Lanes and speed limit of generated roads
+    pub fn roads(mut self, roads: RoadConfig) -> Self {
+        self.config.roads = roads;
+        self
+    }
+
+    // Timing of every light without a signal plan of its own
+    pub fn light_timing(mut self, lights: LightConfig) -> Self {
+        self.config.lights = lights;
+        self
+    }
+
+    // Timing of the light at one position
+    pub fn signal_plan(mut self, position: (i32, i32), plan: SignalPlan) -> Self {
+        self.signal_plans.push((position, plan));
+        self
+    }
+
+    pub fn vehicle_profiles(mut self, profiles: VehicleProfiles) -> Self {
+        self.config.vehicles = profiles;
+        self
+    }
+
+    pub fn spawn_policy(mut self, policy: SpawnPolicy) -> Self {
+        self.config.spawn.policy = policy;
+        self
+    }
+
+    // Hold back new vehicles while this many are on the roads
+    pub fn max_vehicles(mut self, max_vehicles: usize) -> Self {
+        self.config.spawn.max_vehicles = Some(max_vehicles);
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.simulation.seed = Some(seed);
+        self
+    }
+
+    // Simulated seconds per tick
+    pub fn dt(mut self, dt: f32) -> Self {
+        self.config.simulation.dt = dt;
+        self
+    }
+
+    // Stop the run after this many ticks
+    pub fn ticks(mut self, ticks: u64) -> Self {
+        self.config.run.ticks = Some(ticks);
+        self
+    }
+
+    // Stop the run after this many simulated seconds
+    pub fn until_time(mut self, time: f64) -> Self {
+        self.config.run.until_time = Some(time);
+        self
+    }
+
+    // Report overlapping vehicles after every tick
+    pub fn check_occupancy(mut self, check: bool) -> Self {
+        self.config.simulation.check_occupancy = check;
+        self
+    }
+
+    // Carry on from a snapshot written by Simulation::save_snapshot.
+    // Everything but the run section comes from the snapshot, and the
+    // run limits still count from the start of the original run.
+    pub fn resume_from(mut self, path: &str) -> Self {
+        self.resume_from = Some(path.to_string());
+        self
+    }
+
+    // Run a scenario: its network, vehicles and timeline replace the grid
+    // settings and the spawn policy
+    pub fn scenario(mut self, scenario: Scenario) -> Self {
+        self.scenario = Some(scenario);
+        self
+    }
+
+    // Log what happens in every tick, to replay the run later
+    pub fn event_log(mut self, path: &str) -> Self {
+        self.event_log = Some(path.to_string());
+        self
+    }
+
+    pub fn analyzer(mut self, analyzer: impl Analyzer + 'static) -> Self {
+        self.analyzers.push(Box::new(analyzer));
+        self
+    }
+
+    pub fn renderer(mut self, renderer: impl Renderer + 'static) -> Self {
+        self.renderers.push(Box::new(renderer));
+        self
+    }
+
+    // Check the settings, then resume from the snapshot or load or generate the network
+    pub fn build(mut self) -> Result<Simulation, BuildError> {
+        let mut config = self.config;
+        if let Some(scenario) = &self.scenario {
+            config.grid = scenario.grid.clone();
+            // Plans set on the builder win over the scenario's
+            let mut plans = scenario.plans();
+            plans.append(&mut self.signal_plans);
+            self.signal_plans = plans;
+        }
+        config.validate().map_err(BuildError::Config)?;
+        if let Some(path) = &self.resume_from {
+            let mut snapshot = Snapshot::load(Path::new(path)).map_err(BuildError::Snapshot)?;
+            snapshot.config.run = config.run;
+            let simulation = snapshot.restore();
+            return Self::attach(simulation, self.analyzers, self.renderers, self.event_log.as_deref());
+        }
+        for (position, plan) in &self.signal_plans {
+            let durations = [plan.green_duration, plan.yellow_duration, plan.red_duration];
+            if durations.iter().any(|duration| duration.is_nan() || *duration <= 0.0) {
+                return Err(BuildError::InvalidSignalPlan {
+                    position: *position,
+                    message: "durations must be positive numbers of seconds".to_string(),
+                });
+            }
+            // North-south traffic gets green for red minus yellow
+            if plan.yellow_duration >= plan.red_duration {
+                return Err(BuildError::InvalidSignalPlan {
+                    position: *position,
+                    message: "yellow_duration must be shorter than red_duration".to_string(),
+                });
+            }
+            if !plan.offset.is_finite() {
+                return Err(BuildError::InvalidSignalPlan {
+                    position: *position,
+                    message: "offset must be a number of seconds".to_string(),
+                });
+            }
+        }
+
+        // Every random choice of the run comes from this generator
+        let seed = config.simulation.seed.unwrap_or_else(|| rand::rng().random());
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+
+        // Load the network or OSM file if one is configured,
+        // otherwise generate height by width grid of cells
+        let mut grid = if let Some(path) = &config.grid.network_file {
+            Grid::load_network(Path::new(path), &config.lights, &config.roads, &mut rng)
+                .map_err(BuildError::Network)?
+        } else if let Some(path) = &config.grid.osm_file {
+            Grid::load_osm(Path::new(path), &config.lights, &config.roads, config.grid.meters_per_unit, &mut rng)
+                .map_err(BuildError::Network)?
+        } else {
+            Grid::generate_grid(Grid::new(), config.grid.height, config.grid.width, &config.lights, &config.roads, &mut rng)
+        };
+
+        for (position, plan) in &self.signal_plans {
+            let light = grid.traffic_lights.iter_mut()
+                .find(|light| light.position == *position)
+                .ok_or(BuildError::NoLightAt(*position))?;
+            light.apply_plan(plan);
+        }
+
+        let boundary = if config.boundary.enabled {
+            let boundary = Boundary::new(&grid.network, &config.boundary.sources, &config.boundary.sinks)
+                .map_err(|message| BuildError::Config(ConfigError::Invalid { key: "boundary".to_string(), message }))?;
+            Some(boundary)
+        } else {
+            None
+        };
+
+        // Only known once the network is there. Vehicles enter an open
+        // network at its sources and leave it at its sinks only.
+        let mut demand = None;
+        match &config.spawn.policy {
+            SpawnPolicy::Poisson { entry_points, .. } => {
+                for &position in entry_points {
+                    let message = match grid.network.point_at(position) {
+                        None => format!("no point at ({},{})", position.0, position.1),
+                        Some(point) if boundary.as_ref().is_some_and(|boundary| !boundary.sources.contains(&point)) => {
+                            format!("({},{}) is not a source of the boundary", position.0, position.1)
+                        },
+                        Some(_) => continue,
+                    };
+                    return Err(BuildError::Config(ConfigError::Invalid {
+                        key: "spawn.entry_points".to_string(),
+                        message,
+                    }));
+                }
+            },
+            SpawnPolicy::OdMatrix { file, zones } => {
+                let matrix = DemandFile::load(Path::new(file)).map_err(BuildError::Demand)?;
+                let resolved = matrix.resolve(zones, &grid.network).map_err(BuildError::Demand)?;
+                if let Some(boundary) = &boundary {
+                    boundary.check_demand(&resolved, &grid.network).map_err(BuildError::Demand)?;
+                }
+                demand = Some(resolved);
+            },
+            _ => {},
+        }
+
+        let mut next_vehicle_id = 0;
+        let scenario = match &self.scenario {
+            Some(scenario) => Some(
+                scenario.start(&mut grid, &config.vehicles, &mut next_vehicle_id)
+                    .map_err(BuildError::Scenario)?
+            ),
+            None => None,
+        };
+
+        let mut simulation = Simulation::with_grid(config, grid, rng, seed);
+        simulation.next_vehicle_id = next_vehicle_id;
+        simulation.scenario = scenario;
+        simulation.demand = demand;
+        simulation.boundary = boundary;
+        Self::attach(simulation, self.analyzers, self.renderers, self.event_log.as_deref())
+    }
+
+    // Hook the analyzers, renderers and event log up to a built simulation
+    fn attach(
+        mut simulation: Simulation,
+        analyzers: Vec<Box<dyn Analyzer>>,
+        renderers: Vec<Box<dyn Renderer>>,
+        event_log: Option<&str>,
+    ) -> Result<Simulation, BuildError> {
+        if let Some(path) = event_log {
+            let log = EventLog::create(
+                Path::new(path),
+                simulation.seed,
+                simulation.clock.dt,
+                simulation.clock.tick,
+                &simulation.grid,
+            ).map_err(BuildError::EventLog)?;
+            simulation.event_log = Some(log);
+        }
+        simulation.analyzers = analyzers;
+        simulation.renderers = renderers;
+        Ok(simulation)
+    }
+}
+
+impl Display for BuildError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildError::Config(e) => write!(f, "{}", e),
+            BuildError::Network(e) => write!(f, "{}", e),
+            BuildError::NoLightAt((x, y)) => write!(f, "signal plan for ({},{}): no traffic light there", x, y),
+            BuildError::InvalidSignalPlan { position: (x, y), message } => {
+                write!(f, "signal plan for ({},{}): {}", x, y, message)
+            },
+            BuildError::Snapshot(e) => write!(f, "{}", e),
+            BuildError::EventLog(e) => write!(f, "{}", e),
+            BuildError::Scenario(message) => write!(f, "invalid scenario: {}", message),
+            BuildError::Demand(e) => write!(f, "{}", e),
+        }
+    }
+}
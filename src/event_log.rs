@@ -0,0 +1,309 @@
+// event_log.rs
+use crate::helpers::grid::Grid;
+use crate::helpers::light::{LightState, TrafficLight};
+use crate::helpers::vehicle::Vehicle;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+// Ticks between two keyframes of a log
+pub const KEYFRAME_INTERVAL: u64 = 100;
+
+// One line of an event log (JSON Lines): the grid as the log starts,
+// then what changed in every tick
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+pub enum LogRecord {
+    Start { seed: u64, dt: f32, tick: u64, grid: Grid },
+    Tick { tick: u64, events: Vec<Event> },
+    // Everything but the network after a tick, every KEYFRAME_INTERVAL
+    // ticks, so replays can go back without reading from the start
+    Keyframe { tick: u64, vehicles: Vec<Vehicle>, traffic_lights: Vec<TrafficLight>, closed: Vec<usize> },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    Spawned { vehicle: Vehicle },
+    // Only for vehicles whose position, lane or speed changed
+    Moved {
+        vehicle_id: u64,
+        position: (f32, f32),
+        lane: u8,
+        edge_progress: f32,
+        route_index: usize,
+        speed: f32,
+    },
+    Rerouted { vehicle_id: u64, route: Vec<usize> },
+    LightChanged { position: (i32, i32), state: LightState },
+    // A road closed or opened again
+    EdgeClosed { edge: usize, closed: bool },
+    Arrived { vehicle_id: u64 },
+}
+
+#[derive(Debug)]
+pub enum EventLogError {
+    // The file could not be read or written
+    Io(String),
+    // A line is not a log record
+    Format(String),
+}
+
+// Writes the log of a run, comparing the grid after every tick
+// with the one of the tick before
+pub struct EventLog {
+    writer: BufWriter<File>,
+    vehicles: HashMap<u64, Vehicle>,
+    lights: Vec<LightState>,
+    closed: Vec<bool>,
+}
+
+impl EventLog {
+    // Start a log with the current state of the grid
+    pub fn create(path: &Path, seed: u64, dt: f32, tick: u64, grid: &Grid) -> Result<EventLog, EventLogError> {
+        let file = File::create(path).map_err(|e| EventLogError::Io(format!("{}: {}", path.display(), e)))?;
+        let mut log = EventLog {
+            writer: BufWriter::new(file),
+            vehicles: HashMap::new(),
+            lights: Vec::new(),
+            closed: Vec::new(),
+        };
+        log.remember(grid);
+        log.write(&LogRecord::Start { seed, dt, tick, grid: grid.clone() })?;
+        Ok(log)
+    }
+
+    // Append what changed since the last tick
+    pub fn record(&mut self, tick: u64, grid: &Grid) -> Result<(), EventLogError> {
+        let mut events = Vec::new();
+        for vehicle in &grid.vehicles {
+            let Some(last) = self.vehicles.get(&vehicle.id) else {
+                events.push(Event::Spawned { vehicle: vehicle.clone() });
+                continue;
+            };
+            // Before the move, which refers to the new route
+            if vehicle.route != last.route {
+                events.push(Event::Rerouted { vehicle_id: vehicle.id, route: vehicle.route.clone() });
+            }
+            let moved = vehicle.current_position != last.current_position
+                || vehicle.lane != last.lane
+                || vehicle.edge_progress != last.edge_progress
+                || vehicle.route_index != last.route_index
+                || vehicle.current_speed != last.current_speed;
+            if moved {
+                events.push(Event::Moved {
+                    vehicle_id: vehicle.id,
+                    position: vehicle.current_position,
+                    lane: vehicle.lane,
+                    edge_progress: vehicle.edge_progress,
+                    route_index: vehicle.route_index,
+                    speed: vehicle.current_speed,
+                });
+            }
+        }
+        let mut arrived: Vec<u64> = self.vehicles.keys()
+            .filter(|id| !grid.vehicles.iter().any(|vehicle| vehicle.id == **id))
+            .copied()
+            .collect();
+        // Same log for the same run, whatever order the map keeps
+        arrived.sort_unstable();
+        events.extend(arrived.into_iter().map(|vehicle_id| Event::Arrived { vehicle_id }));
+        for (light, last) in grid.traffic_lights.iter().zip(&self.lights) {
+            if light.light_state != *last {
+                events.push(Event::LightChanged { position: light.position, state: light.light_state.clone() });
+            }
+        }
+        for (index, (edge, last)) in grid.network.edges.iter().zip(&self.closed).enumerate() {
+            if edge.closed != *last {
+                events.push(Event::EdgeClosed { edge: index, closed: edge.closed });
+            }
+        }
+
+        self.remember(grid);
+        self.write(&LogRecord::Tick { tick, events })?;
+        if tick.is_multiple_of(KEYFRAME_INTERVAL) {
+            self.write(&LogRecord::Keyframe {
+                tick,
+                vehicles: grid.vehicles.clone(),
+                traffic_lights: grid.traffic_lights.clone(),
+                closed: grid.network.edges.iter()
+                    .enumerate()
+                    .filter(|(_, edge)| edge.closed)
+                    .map(|(index, _)| index)
+                    .collect(),
+            })?;
+        }
+        Ok(())
+    }
+
+    fn remember(&mut self, grid: &Grid) {
+        self.vehicles = grid.vehicles.iter().map(|vehicle| (vehicle.id, vehicle.clone())).collect();
+        self.lights = grid.traffic_lights.iter().map(|light| light.light_state.clone()).collect();
+        self.closed = grid.network.edges.iter().map(|edge| edge.closed).collect();
+    }
+
+    fn write(&mut self, record: &LogRecord) -> Result<(), EventLogError> {
+        let io_error = |e: std::io::Error| EventLogError::Io(e.to_string());
+        serde_json::to_writer(&mut self.writer, record).map_err(|e| EventLogError::Format(e.to_string()))?;
+        writeln!(self.writer).map_err(io_error)?;
+        // Flushed every tick, so the log of a crashed run is still usable
+        self.writer.flush().map_err(io_error)
+    }
+}
+
+// Rebuilds the grid of a logged run tick by tick, without simulating it
+pub struct Replay {
+    pub seed: u64,
+    pub dt: f32,
+    pub tick: u64,
+    pub grid: Grid,
+    reader: BufReader<File>,
+    // Where in the file the next line starts
+    offset: u64,
+    // Tick and file offset of the start record and of every keyframe
+    // read so far, in tick order
+    keyframes: Vec<(u64, u64)>,
+}
+
+impl Replay {
+    pub fn open(path: &Path) -> Result<Replay, EventLogError> {
+        let file = File::open(path).map_err(|e| EventLogError::Io(format!("{}: {}", path.display(), e)))?;
+        let mut reader = BufReader::new(file);
+        let mut offset = 0;
+        match Replay::read(&mut reader, &mut offset)? {
+            Some((_, LogRecord::Start { seed, dt, tick, grid })) => {
+                Ok(Replay { seed, dt, tick, grid, reader, offset, keyframes: vec![(tick, 0)] })
+            },
+            _ => Err(EventLogError::Format(format!("{}: does not start with a start record", path.display()))),
+        }
+    }
+
+    // Apply the next tick of the log, false at its end
+    pub fn next_tick(&mut self) -> Result<bool, EventLogError> {
+        loop {
+            match Replay::read(&mut self.reader, &mut self.offset)? {
+                Some((_, LogRecord::Tick { tick, events })) => {
+                    self.tick = tick;
+                    for event in events {
+                        self.apply(event);
+                    }
+                    return Ok(true);
+                },
+                Some((offset, LogRecord::Keyframe { tick, vehicles, traffic_lights, closed })) => {
+                    if self.keyframes.last().is_some_and(|&(last, _)| last < tick) {
+                        self.keyframes.push((tick, offset));
+                    }
+                    self.restore(tick, vehicles, traffic_lights, closed);
+                },
+                Some((_, LogRecord::Start { .. })) => return Err(EventLogError::Format("second start record".to_string())),
+                None => return Ok(false),
+            }
+        }
+    }
+
+    // Go to a tick of the log, or to its start or end if it lies outside.
+    // Going back starts again from the last keyframe before the tick.
+    pub fn seek(&mut self, tick: u64) -> Result<(), EventLogError> {
+        let (keyframe_tick, offset) = self.keyframes.iter()
+            .rev()
+            .find(|(keyframe_tick, _)| *keyframe_tick <= tick)
+            .copied()
+            .unwrap_or(self.keyframes[0]);
+        if tick < self.tick || keyframe_tick > self.tick {
+            self.jump_to(offset)?;
+        }
+        while self.tick < tick && self.next_tick()? {}
+        Ok(())
+    }
+
+    // Continue from the start record or keyframe at this offset
+    fn jump_to(&mut self, offset: u64) -> Result<(), EventLogError> {
+        self.reader.seek(SeekFrom::Start(offset)).map_err(|e| EventLogError::Io(e.to_string()))?;
+        self.offset = offset;
+        match Replay::read(&mut self.reader, &mut self.offset)? {
+            Some((_, LogRecord::Start { tick, grid, .. })) => {
+                self.tick = tick;
+                self.grid = grid;
+            },
+            Some((_, LogRecord::Keyframe { tick, vehicles, traffic_lights, closed })) => {
+                self.restore(tick, vehicles, traffic_lights, closed);
+            },
+            _ => return Err(EventLogError::Format(format!("no keyframe at byte {}", offset))),
+        }
+        Ok(())
+    }
+
+    fn restore(&mut self, tick: u64, vehicles: Vec<Vehicle>, traffic_lights: Vec<TrafficLight>, closed: Vec<usize>) {
+        self.tick = tick;
+        self.grid.vehicles = vehicles;
+        self.grid.traffic_lights = traffic_lights;
+        for edge in 0..self.grid.network.edges.len() {
+            let closed = closed.contains(&edge);
+            if self.grid.network.edges[edge].closed != closed {
+                self.grid.set_edge_closed(edge, closed);
+            }
+        }
+    }
+
+    fn apply(&mut self, event: Event) {
+        let grid = &mut self.grid;
+        match event {
+            Event::Spawned { vehicle } => grid.vehicles.push(vehicle),
+            Event::Moved { vehicle_id, position, lane, edge_progress, route_index, speed } => {
+                if let Some(vehicle) = grid.vehicles.iter_mut().find(|vehicle| vehicle.id == vehicle_id) {
+                    vehicle.current_position = position;
+                    vehicle.lane = lane;
+                    vehicle.edge_progress = edge_progress;
+                    vehicle.route_index = route_index;
+                    vehicle.current_speed = speed;
+                }
+            },
+            Event::Rerouted { vehicle_id, route } => {
+                if let Some(vehicle) = grid.vehicles.iter_mut().find(|vehicle| vehicle.id == vehicle_id) {
+                    vehicle.route = route;
+                }
+            },
+            Event::LightChanged { position, state } => {
+                if let Some(light) = grid.traffic_lights.iter_mut().find(|light| light.position == position) {
+                    light.light_state = state;
+                    light.time_in_state = 0.0;
+                }
+            },
+            Event::EdgeClosed { edge, closed } => grid.set_edge_closed(edge, closed),
+            Event::Arrived { vehicle_id } => grid.vehicles.retain(|vehicle| vehicle.id != vehicle_id),
+        }
+    }
+
+    // Read the record at the offset and move the offset past it.
+    // Returns the record with the offset its line starts at.
+    fn read(reader: &mut BufReader<File>, offset: &mut u64) -> Result<Option<(u64, LogRecord)>, EventLogError> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let start = *offset;
+            let read = reader.read_line(&mut line).map_err(|e| EventLogError::Io(e.to_string()))?;
+            if read == 0 {
+                return Ok(None);
+            }
+            *offset += read as u64;
+            if line.trim().is_empty() {
+                continue;
+            }
+            return serde_json::from_str(&line)
+                .map(|record| Some((start, record)))
+                .map_err(|e| EventLogError::Format(e.to_string()));
+        }
+    }
+}
+
+impl Display for EventLogError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EventLogError::Io(message) => write!(f, "could not access event log {}", message),
+            EventLogError::Format(message) => write!(f, "invalid event log: {}", message),
+        }
+    }
+}
@@ -1,12 +1,166 @@
 use tokio::sync::mpsc;
 use tokio::time::{sleep, Duration};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
 use super::message::SimulationMessage;
+use super::vehicle::VehicleType;
 
+// Consumer of the simulation messages that runs inside the simulation,
+// called with every message as it is sent
+pub trait Analyzer: Send {
+    fn record(&mut self, message: &SimulationMessage);
 
-pub async fn run_analyzer(mut rx: mpsc::Receiver<SimulationMessage>) {
+    // Results printed at the end of a run
+    fn report(&self) -> String {
+        String::new()
+    }
+}
+
+// Totals of a run, gathered from the messages
+#[derive(Debug, Default)]
+pub struct RunSummary {
+    pub ticks: u64,
+    // Simulated seconds
+    pub simulated_time: f64,
+    pub vehicles_spawned: u64,
+    // Still driving when the run stopped
+    pub vehicles_on_road: usize,
+    // Still waiting to enter an open network
+    pub vehicles_queued: usize,
+    pub reroutes: u64,
+    // Mean over the ticks of the average vehicle speed
+    pub average_speed: f32,
+    // Ticks seen, resumed runs don't start at tick 1
+    pub samples: u64,
+    // Finished trips by vehicle type
+    pub trips: BTreeMap<VehicleType, TripStats>,
+}
+
+// Totals of the finished trips of one vehicle type, times in simulated
+// seconds. Trips given up on only add to `abandoned`.
+#[derive(Debug, Default)]
+pub struct TripStats {
+    pub count: u64,
+    pub abandoned: u64,
+    pub travel_time: f64,
+    pub delay: f64,
+    pub stopped_time: f64,
+    pub distance: f64,
+}
+
+impl TripStats {
+    pub fn average_travel_time(&self) -> f64 {
+        self.travel_time / self.count.max(1) as f64
+    }
+
+    pub fn average_delay(&self) -> f64 {
+        self.delay / self.count.max(1) as f64
+    }
+
+    pub fn average_stopped_time(&self) -> f64 {
+        self.stopped_time / self.count.max(1) as f64
+    }
+
+    pub fn average_distance(&self) -> f64 {
+        self.distance / self.count.max(1) as f64
+    }
+}
+
+impl RunSummary {
+    // Trips finished by all vehicle types
+    pub fn trips_finished(&self) -> u64 {
+        self.trips.values().map(|stats| stats.count).sum()
+    }
+
+    // Trips given up on by all vehicle types
+    pub fn trips_abandoned(&self) -> u64 {
+        self.trips.values().map(|stats| stats.abandoned).sum()
+    }
+}
+
+impl Analyzer for RunSummary {
+    fn record(&mut self, message: &SimulationMessage) {
+        match message {
+            SimulationMessage::GridUpdate { tick, time, vehicle_count, average_speed, queued, .. } => {
+                self.ticks = *tick;
+                self.simulated_time = *time;
+                self.vehicles_on_road = *vehicle_count;
+                self.vehicles_queued = *queued;
+                // Running mean, one sample per tick
+                self.samples += 1;
+                self.average_speed += (average_speed - self.average_speed) / self.samples as f32;
+            },
+            SimulationMessage::RoutePlanned { .. } => self.vehicles_spawned += 1,
+            SimulationMessage::Rerouted { .. } => self.reroutes += 1,
+            SimulationMessage::TripCompleted {
+                vehicle_type, abandoned, travel_time, delay, stopped_time, distance, ..
+            } => {
+                let stats = self.trips.entry(vehicle_type.clone()).or_default();
+                if *abandoned {
+                    stats.abandoned += 1;
+                    return;
+                }
+                stats.count += 1;
+                stats.travel_time += *travel_time as f64;
+                stats.delay += *delay as f64;
+                stats.stopped_time += *stopped_time as f64;
+                stats.distance += *distance as f64;
+            },
+        }
+    }
+
+    fn report(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Display for RunSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Ticks:            {}", self.ticks)?;
+        writeln!(f, "Simulated time:   {:.1} s", self.simulated_time)?;
+        writeln!(f, "Vehicles spawned: {}", self.vehicles_spawned)?;
+        writeln!(f, "Trips finished:   {}", self.trips_finished())?;
+        if self.trips_abandoned() > 0 {
+            writeln!(f, "Trips given up:   {}", self.trips_abandoned())?;
+        }
+        writeln!(f, "Still driving:    {}", self.vehicles_on_road)?;
+        if self.vehicles_queued > 0 {
+            writeln!(f, "Waiting to enter: {}", self.vehicles_queued)?;
+        }
+        writeln!(f, "Reroutes:         {}", self.reroutes)?;
+        write!(f, "Average speed:    {:.2} units/s", self.average_speed)?;
+        // Per vehicle type averages of the finished trips
+        for (vehicle_type, stats) in &self.trips {
+            write!(f, "\n  {:<10} {:>6} trips", format!("{:?}", vehicle_type), stats.count)?;
+            if stats.count > 0 {
+                write!(
+                    f,
+                    ", travel time {:.1} s, delay {:.1} s, stopped {:.1} s, distance {:.1}",
+                    stats.average_travel_time(),
+                    stats.average_delay(),
+                    stats.average_stopped_time(),
+                    stats.average_distance(),
+                )?;
+            }
+            if stats.abandoned > 0 {
+                write!(f, ", {} given up", stats.abandoned)?;
+            }
+        }
+        writeln!(f)
+    }
+}
+
+// Print every message as it comes (verbose) or just add it up,
+// returning the totals once the simulation closes the channel
+pub async fn run_analyzer(mut rx: mpsc::Receiver<SimulationMessage>, verbose: bool) -> RunSummary {
+    let mut summary = RunSummary::default();
     while let Some(message) = rx.recv().await {
-        println!("Analyzer received: {:?}", message);
-        // Simulate some processing time (e.g., 50ms)
-        sleep(Duration::from_millis(50)).await;
+        summary.record(&message);
+        if verbose {
+            println!("Analyzer received: {:?}", message);
+            // Simulate some processing time (e.g., 50ms)
+            sleep(Duration::from_millis(50)).await;
+        }
     }
-}
\ No newline at end of file
+    summary
+}
@@ -0,0 +1,137 @@
+// boundary.rs
+use super::demand::{Demand, DemandError};
+use super::grid::Grid;
+use super::network::RoadNetwork;
+use super::spawn::Arrival;
+use super::vehicle::Vehicle;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+
+// Entry and exit points of an open network. Vehicles enter at a source,
+// leave at a sink, and wait outside the network while their entry is full.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Boundary {
+    // Point indexes
+    pub sources: Vec<usize>,
+    pub sinks: Vec<usize>,
+    // Vehicles waiting to enter, by start point, first come first in
+    pub queues: BTreeMap<usize, VecDeque<Vehicle>>,
+}
+
+impl Boundary {
+    // Sources and sinks at the given positions, the perimeter when left out.
+    // Given ones may be any point, e.g. a car park inside the network.
+    pub fn new(
+        network: &RoadNetwork,
+        sources: &[(i32, i32)],
+        sinks: &[(i32, i32)],
+    ) -> Result<Boundary, String> {
+        let resolve = |positions: &[(i32, i32)]| -> Result<Vec<usize>, String> {
+            if positions.is_empty() {
+                return Ok(perimeter(network));
+            }
+            positions.iter()
+                .map(|&position| {
+                    network.point_at(position)
+                        .ok_or_else(|| format!("no point at ({},{})", position.0, position.1))
+                })
+                .collect()
+        };
+        let boundary = Boundary {
+            sources: resolve(sources)?,
+            sinks: resolve(sinks)?,
+            queues: BTreeMap::new(),
+        };
+        // Vehicles leave through a sink other than the point they came in at
+        let stuck = boundary.sources.iter().find(|&&source| boundary.sinks.iter().all(|&sink| sink == source));
+        if let Some(&source) = stuck {
+            let (x, y) = network.position(source);
+            return Err(format!("no sink to leave through for the source at ({},{})", x, y));
+        }
+        Ok(boundary)
+    }
+
+    // Start at a source and head for a sink, unless the arrival says
+    // otherwise. False when the only sink is where the arrival starts,
+    // which then has no trip to make.
+    pub fn fill(&self, arrival: &mut Arrival, rng: &mut impl Rng) -> bool {
+        let start = *arrival.start.get_or_insert_with(|| self.sources[rng.random_range(0..self.sources.len())]);
+        if arrival.destination.is_none() {
+            // Not the sink it came in through
+            let sinks: Vec<usize> = self.sinks.iter().copied().filter(|&sink| sink != start).collect();
+            if sinks.is_empty() {
+                return false;
+            }
+            arrival.destination = Some(sinks[rng.random_range(0..sinks.len())]);
+        }
+        true
+    }
+
+    // Let a new vehicle in, or queue it behind those already waiting at its
+    // start. Returns whether it is on the grid now.
+    pub fn enter(&mut self, vehicle: Vehicle, grid: &mut Grid) -> bool {
+        let start = grid.network.point_at(vehicle.rounded_position());
+        let queue = start.and_then(|start| self.queues.get(&start));
+        if queue.is_none_or(|queue| queue.is_empty()) && grid.add_vehicle(vehicle.clone()) {
+            return true;
+        }
+        if let Some(start) = start {
+            self.queues.entry(start).or_default().push_back(vehicle);
+        }
+        false
+    }
+
+    // Every trip of an origin-destination matrix starts at a source and
+    // ends at a sink
+    pub fn check_demand(&self, demand: &Demand, network: &RoadNetwork) -> Result<(), DemandError> {
+        for pair in &demand.pairs {
+            let outside = [(&pair.origins, &self.sources, "source"), (&pair.destinations, &self.sinks, "sink")]
+                .into_iter()
+                .find_map(|(points, allowed, kind)| {
+                    points.iter().find(|point| !allowed.contains(point)).map(|&point| (point, kind))
+                });
+            if let Some((point, kind)) = outside {
+                let (x, y) = network.position(point);
+                return Err(DemandError::Invalid(format!("({},{}) is not a {} of the boundary", x, y, kind)));
+            }
+        }
+        Ok(())
+    }
+
+    // Move waiting vehicles in while their entry has room, returning their ids
+    pub fn admit(&mut self, grid: &mut Grid) -> Vec<u64> {
+        let mut admitted = Vec::new();
+        for queue in self.queues.values_mut() {
+            while let Some(vehicle) = queue.front() {
+                if !grid.add_vehicle(vehicle.clone()) {
+                    break;
+                }
+                admitted.push(vehicle.id);
+                queue.pop_front();
+            }
+        }
+        self.queues.retain(|_, queue| !queue.is_empty());
+        admitted
+    }
+
+    // Vehicles waiting outside the network
+    pub fn queued(&self) -> usize {
+        self.queues.values().map(|queue| queue.len()).sum()
+    }
+}
+
+// Points on the bounding box of the network
+pub fn perimeter(network: &RoadNetwork) -> Vec<usize> {
+    let points = &network.points;
+    let min_x = points.iter().map(|p| p.x).min().unwrap_or(0);
+    let max_x = points.iter().map(|p| p.x).max().unwrap_or(0);
+    let min_y = points.iter().map(|p| p.y).min().unwrap_or(0);
+    let max_y = points.iter().map(|p| p.y).max().unwrap_or(0);
+    (0..points.len())
+        .filter(|&i| {
+            let p = &points[i];
+            p.x == min_x || p.x == max_x || p.y == min_y || p.y == max_y
+        })
+        .collect()
+}
@@ -0,0 +1,29 @@
+// clock.rs
+use serde::{Deserialize, Serialize};
+
+// Simulated time. It moves by the same step every tick, however long the
+// tick took on the wall clock, so runs don't depend on the machine.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SimulationClock {
+    // Ticks run so far
+    pub tick: u64,
+    // Seconds of simulated time per tick
+    pub dt: f32,
+}
+
+impl SimulationClock {
+    pub fn new(dt: f32) -> Self {
+        Self { tick: 0, dt }
+    }
+
+    // Start the next tick
+    pub fn advance(&mut self) {
+        self.tick += 1;
+    }
+
+    // Simulated seconds since the start, computed from the tick count
+    // so rounding errors don't add up
+    pub fn time(&self) -> f64 {
+        self.tick as f64 * self.dt as f64
+    }
+}
@@ -0,0 +1,443 @@
+// config.rs
+use super::log::LogLevel;
+use super::spawn::{SpawnConfig, SpawnPolicy};
+use super::vehicle::{ReroutePolicy, VehicleType};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+// Everything that used to be a compile-time static or a literal in main,
+// TrafficLight::new and Vehicle::generate_vehicle.
+// Missing keys fall back to the defaults below, unknown keys are rejected.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SimulationConfig {
+    pub grid: GridConfig,
+    pub roads: RoadConfig,
+    pub simulation: TimingConfig,
+    pub lights: LightConfig,
+    pub vehicles: VehicleProfiles,
+    pub spawn: SpawnConfig,
+    pub boundary: BoundaryConfig,
+    pub run: RunConfig,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GridConfig {
+    // Number of rows of points
+    pub height: i32,
+    // Number of columns of points
+    pub width: i32,
+    // JSON network to load instead of generating a height x width lattice
+    pub network_file: Option<String>,
+    // OpenStreetMap XML extract to load instead
+    pub osm_file: Option<String>,
+    // Scale of the OSM projection, how many meters one grid unit covers
+    pub meters_per_unit: f32,
+    // Save the network to this JSON file on startup, to edit it later
+    pub export_network: Option<String>,
+}
+
+// Properties of the generated roads
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RoadConfig {
+    // Lanes in each direction
+    pub lanes: u8,
+    // Units per second
+    pub speed_limit: f32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimingConfig {
+    // Seconds of simulated time per tick, the same for lights and vehicles
+    pub dt: f32,
+    // Wall-clock time between two ticks of the main loop, only paces the
+    // display. 0 runs the ticks back to back.
+    pub tick_interval_ms: u64,
+    // Capacity of the channel between the engine and the analyzer
+    pub channel_capacity: usize,
+    // Check after every tick that no two vehicles share a cell, reporting overlaps
+    pub check_occupancy: bool,
+    // Seed of the random number generator behind spawns and initial light
+    // states. A random one is picked (and shown) when not set.
+    pub seed: Option<u64>,
+}
+
+// Open network: vehicles enter at sources and leave at sinks, waiting
+// outside while their entry is full
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BoundaryConfig {
+    // Off, vehicles start and end at any point
+    pub enabled: bool,
+    // Every point on the edge of the network when left empty. Any point
+    // may be given, inside the network too.
+    pub sources: Vec<(i32, i32)>,
+    pub sinks: Vec<(i32, i32)>,
+}
+
+// How long to run and whether to draw it
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RunConfig {
+    // Skip drawing and pacing, run as fast as possible and print a summary
+    pub headless: bool,
+    // Stop after this many ticks...
+    pub ticks: Option<u64>,
+    // ...or once this many simulated seconds have passed
+    pub until_time: Option<f64>,
+    // Scenario file (.toml or .json) whose network, vehicles and timeline
+    // replace the grid settings and the spawn policy
+    pub scenario: Option<String>,
+    // Carry on from a snapshot file instead of starting a new run
+    pub resume_from: Option<String>,
+    // Write a snapshot to this file when the run stops
+    pub save_snapshot: Option<String>,
+    // Log every tick to this file, to replay the run later
+    pub event_log: Option<String>,
+    // Directory for the snapshot, event log and exported network when
+    // their paths are relative, and for the summary of the run
+    pub output_dir: Option<String>,
+    pub log_level: LogLevel,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LightConfig {
+    // In seconds:
+    pub green_duration: f32,
+    pub yellow_duration: f32,
+    pub red_duration: f32,
+}
+
+// Keys left out of a profile keep the default of its vehicle type
+#[derive(Clone, Debug, Serialize)]
+pub struct VehicleProfiles {
+    pub car: VehicleProfile,
+    pub bus: VehicleProfile,
+    pub emergency: VehicleProfile,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct VehicleProfile {
+    // Top speed in units per second
+    pub speed: f32,
+    // Higher is better
+    pub priority: u8,
+    // Units per second squared: how hard the vehicle speeds up, and the
+    // deceleration it is comfortable with when slowing down
+    pub acceleration: f32,
+    pub braking: f32,
+    // e.g. { policy = "on_threshold", ratio = 1.5 }, { policy = "periodic", every_ticks = 10 }
+    // or { policy = "never" }
+    pub reroute: ReroutePolicy,
+}
+
+// Profiles as written in a config, any key may be missing
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PartialProfiles {
+    car: Option<PartialProfile>,
+    bus: Option<PartialProfile>,
+    emergency: Option<PartialProfile>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PartialProfile {
+    #[serde(default)]
+    speed: Option<f32>,
+    #[serde(default)]
+    priority: Option<u8>,
+    #[serde(default)]
+    acceleration: Option<f32>,
+    #[serde(default)]
+    braking: Option<f32>,
+    #[serde(default)]
+    reroute: Option<ReroutePolicy>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    // The file could not be read
+    Io(String),
+    // The file is not valid TOML/JSON or has unknown/mistyped keys
+    Parse(String),
+    // A value parsed fine but is out of range
+    Invalid { key: String, message: String },
+}
+
+impl Default for GridConfig {
+    fn default() -> Self {
+        Self {
+            height: 3,
+            width: 3,
+            network_file: None,
+            osm_file: None,
+            meters_per_unit: 10.0,
+            export_network: None,
+        }
+    }
+}
+
+impl Default for RoadConfig {
+    fn default() -> Self {
+        Self {
+            lanes: 1,
+            speed_limit: 3.0,
+        }
+    }
+}
+
+impl Default for TimingConfig {
+    fn default() -> Self {
+        Self {
+            dt: 0.3,
+            tick_interval_ms: 300,
+            channel_capacity: 100,
+            check_occupancy: false,
+            seed: None,
+        }
+    }
+}
+
+impl Default for LightConfig {
+    fn default() -> Self {
+        Self {
+            green_duration: 3.0,
+            yellow_duration: 1.0,
+            red_duration: 2.0,
+        }
+    }
+}
+
+impl Default for VehicleProfiles {
+    fn default() -> Self {
+        Self {
+            car: VehicleProfile {
+                speed: 2.0,
+                priority: 1,
+                acceleration: 1.0,
+                braking: 1.5,
+                reroute: ReroutePolicy::default(),
+            },
+            bus: VehicleProfile {
+                speed: 1.0,
+                priority: 2,
+                acceleration: 0.5,
+                braking: 1.0,
+                reroute: ReroutePolicy::default(),
+            },
+            emergency: VehicleProfile {
+                speed: 3.0,
+                priority: 3,
+                acceleration: 1.5,
+                braking: 2.0,
+                reroute: ReroutePolicy::default(),
+            },
+        }
+    }
+}
+
+impl PartialProfile {
+    // The profile with the given defaults for the keys left out
+    fn over(profile: Option<PartialProfile>, defaults: VehicleProfile) -> VehicleProfile {
+        let Some(profile) = profile else {
+            return defaults;
+        };
+        VehicleProfile {
+            speed: profile.speed.unwrap_or(defaults.speed),
+            priority: profile.priority.unwrap_or(defaults.priority),
+            acceleration: profile.acceleration.unwrap_or(defaults.acceleration),
+            braking: profile.braking.unwrap_or(defaults.braking),
+            reroute: profile.reroute.unwrap_or(defaults.reroute),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for VehicleProfiles {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let partial = PartialProfiles::deserialize(deserializer)?;
+        let defaults = VehicleProfiles::default();
+        Ok(VehicleProfiles {
+            car: PartialProfile::over(partial.car, defaults.car),
+            bus: PartialProfile::over(partial.bus, defaults.bus),
+            emergency: PartialProfile::over(partial.emergency, defaults.emergency),
+        })
+    }
+}
+
+impl VehicleProfiles {
+    pub fn profile(&self, vehicle_type: &VehicleType) -> &VehicleProfile {
+        match vehicle_type {
+            VehicleType::Car => &self.car,
+            VehicleType::Bus => &self.bus,
+            VehicleType::Emergency => &self.emergency,
+        }
+    }
+}
+
+impl SimulationConfig {
+    // Load a config from a .toml or .json file and validate it
+    pub fn load(path: &Path) -> Result<SimulationConfig, ConfigError> {
+        let config = SimulationConfig::read(path)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    // Load a config without validating it, to change it before
+    pub fn read(path: &Path) -> Result<SimulationConfig, ConfigError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| ConfigError::Io(format!("{}: {}", path.display(), e)))?;
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => SimulationConfig::from_toml(&contents),
+            Some("json") => SimulationConfig::from_json(&contents),
+            _ => Err(ConfigError::Parse(format!(
+                "{}: expected a .toml or .json file",
+                path.display()
+            ))),
+        }
+    }
+
+    pub fn from_toml(contents: &str) -> Result<SimulationConfig, ConfigError> {
+        toml::from_str(contents).map_err(|e| ConfigError::Parse(e.to_string()))
+    }
+
+    pub fn from_json(contents: &str) -> Result<SimulationConfig, ConfigError> {
+        serde_json::from_str(contents).map_err(|e| ConfigError::Parse(e.to_string()))
+    }
+
+    // Check ranges, reporting the first bad key
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.grid.height < 1 {
+            return Err(invalid("grid.height", "must be at least 1"));
+        }
+        if self.grid.width < 1 {
+            return Err(invalid("grid.width", "must be at least 1"));
+        }
+        if self.grid.network_file.is_some() && self.grid.osm_file.is_some() {
+            return Err(invalid("grid.osm_file", "can't be used together with grid.network_file"));
+        }
+        if self.grid.meters_per_unit.is_nan() || self.grid.meters_per_unit <= 0.0 {
+            return Err(invalid("grid.meters_per_unit", "must be a positive number of meters"));
+        }
+        if self.roads.lanes < 1 {
+            return Err(invalid("roads.lanes", "must be at least 1"));
+        }
+        if self.roads.speed_limit.is_nan() || self.roads.speed_limit <= 0.0 {
+            return Err(invalid("roads.speed_limit", "must be a positive number of units per second"));
+        }
+        if !self.simulation.dt.is_finite() || self.simulation.dt <= 0.0 {
+            return Err(invalid("simulation.dt", "must be a positive number of seconds"));
+        }
+        if self.simulation.channel_capacity == 0 {
+            return Err(invalid("simulation.channel_capacity", "must be greater than 0"));
+        }
+
+        if let Some(until_time) = self.run.until_time {
+            if !until_time.is_finite() || until_time <= 0.0 {
+                return Err(invalid("run.until_time", "must be a positive number of seconds"));
+            }
+        }
+        if self.run.headless && self.run.ticks.is_none() && self.run.until_time.is_none() {
+            return Err(invalid("run.headless", "needs run.ticks or run.until_time to know when to stop"));
+        }
+
+        let durations = [
+            ("lights.green_duration", self.lights.green_duration),
+            ("lights.yellow_duration", self.lights.yellow_duration),
+            ("lights.red_duration", self.lights.red_duration),
+        ];
+        for (key, duration) in durations {
+            if duration.is_nan() || duration <= 0.0 {
+                return Err(invalid(key, "must be a positive number of seconds"));
+            }
+        }
+        // North-south traffic gets green for red minus yellow
+        if self.lights.yellow_duration >= self.lights.red_duration {
+            return Err(invalid("lights.yellow_duration", "must be shorter than lights.red_duration"));
+        }
+
+        match &self.spawn.policy {
+            SpawnPolicy::PerTick { .. } => {},
+            SpawnPolicy::OdMatrix { zones, .. } => {
+                if let Some(name) = zones.iter().find(|(_, points)| points.is_empty()).map(|(name, _)| name) {
+                    return Err(invalid(&format!("spawn.zones.{}", name), "needs at least one point"));
+                }
+            },
+            SpawnPolicy::FixedRate { rate } | SpawnPolicy::Poisson { rate, .. } => {
+                if !rate.is_finite() || *rate < 0.0 {
+                    return Err(invalid("spawn.rate", "must be a number of vehicles per second, 0 or more"));
+                }
+            },
+            SpawnPolicy::Curve { points, period } => {
+                if points.is_empty() {
+                    return Err(invalid("spawn.points", "needs at least one [time, rate] pair"));
+                }
+                if points.iter().any(|(time, rate)| !time.is_finite() || !rate.is_finite() || *rate < 0.0) {
+                    return Err(invalid("spawn.points", "rates must be numbers of vehicles per second, 0 or more"));
+                }
+                if points.windows(2).any(|pair| pair[1].0 < pair[0].0) {
+                    return Err(invalid("spawn.points", "must be sorted by time"));
+                }
+                if period.is_some_and(|period| !period.is_finite() || period <= 0.0) {
+                    return Err(invalid("spawn.period", "must be a positive number of seconds"));
+                }
+            },
+        }
+
+        let profiles = [
+            ("vehicles.car", &self.vehicles.car),
+            ("vehicles.bus", &self.vehicles.bus),
+            ("vehicles.emergency", &self.vehicles.emergency),
+        ];
+        for (key, profile) in profiles {
+            let rates = [
+                ("speed", profile.speed, "must be a positive number of units per second"),
+                ("acceleration", profile.acceleration, "must be a positive number of units per second squared"),
+                ("braking", profile.braking, "must be a positive number of units per second squared"),
+            ];
+            for (name, value, message) in rates {
+                if value.is_nan() || value <= 0.0 {
+                    return Err(invalid(&format!("{}.{}", key, name), message));
+                }
+            }
+            match profile.reroute {
+                ReroutePolicy::Periodic { every_ticks } if every_ticks < 1 => {
+                    return Err(invalid(&format!("{}.reroute.every_ticks", key), "must be at least 1"));
+                },
+                ReroutePolicy::OnThreshold { ratio } if ratio.is_nan() || ratio <= 1.0 => {
+                    return Err(invalid(&format!("{}.reroute.ratio", key), "must be greater than 1"));
+                },
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn invalid(key: &str, message: &str) -> ConfigError {
+    ConfigError::Invalid {
+        key: key.to_string(),
+        message: message.to_string(),
+    }
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(message) => write!(f, "could not read config: {}", message),
+            ConfigError::Parse(message) => write!(f, "could not parse config: {}", message),
+            ConfigError::Invalid { key, message } => {
+                write!(f, "invalid value for `{}`: {}", key, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
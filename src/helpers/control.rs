@@ -0,0 +1,207 @@
+// control.rs
+use std::io::BufRead;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TryRecvError;
+use tokio::time::{Interval, MissedTickBehavior};
+
+// Commands sent to the main loop while it runs
+#[derive(Clone, Debug, PartialEq)]
+pub enum ControlCommand {
+    Pause,
+    Resume,
+    // Pause and run this many ticks
+    Step(u32),
+    // Multiplier of the configured pace, 2.0 runs twice as fast
+    SetSpeed(f32),
+    // Pause at another tick, only in replays
+    Jump(Jump),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Jump {
+    // This many ticks back
+    Back(u64),
+    // To this tick
+    To(u64),
+}
+
+// Keys understood on the keyboard, each followed by Enter
+pub const KEY_HELP: &str = "p pause | r resume | s [n] step | x <speed> e.g. x 0.5, x 10";
+// Replays also go back and forth in the log
+pub const REPLAY_KEY_HELP: &str = "p pause | r resume | s [n] step | b [n] back | g <tick> go to | x <speed>";
+
+impl ControlCommand {
+    // Parse one line typed by the user
+    pub fn parse(line: &str) -> Option<ControlCommand> {
+        let mut words = line.split_whitespace();
+        let command = match words.next()? {
+            "p" => ControlCommand::Pause,
+            "r" => ControlCommand::Resume,
+            "s" => match words.next() {
+                Some(ticks) => ControlCommand::Step(ticks.parse().ok().filter(|&n| n > 0)?),
+                None => ControlCommand::Step(1),
+            },
+            "b" => match words.next() {
+                Some(ticks) => ControlCommand::Jump(Jump::Back(ticks.parse().ok().filter(|&n| n > 0)?)),
+                None => ControlCommand::Jump(Jump::Back(1)),
+            },
+            "g" => ControlCommand::Jump(Jump::To(words.next()?.parse().ok()?)),
+            "x" => {
+                let speed: f32 = words.next()?.trim_end_matches('x').parse().ok()?;
+                if !speed.is_finite() || speed <= 0.0 {
+                    return None;
+                }
+                ControlCommand::SetSpeed(speed)
+            },
+            _ => return None,
+        };
+        Some(command)
+    }
+}
+
+// Read commands from stdin on a thread of its own. Tokio's stdin would keep
+// the runtime from shutting down while it waits for a line.
+pub fn spawn_keyboard_reader(tx: mpsc::Sender<ControlCommand>) {
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+            if let Some(command) = ControlCommand::parse(&line) {
+                if tx.blocking_send(command).is_err() {
+                    // The simulation is over
+                    break;
+                }
+            }
+        }
+    });
+}
+
+// Pace of the main loop, changed by the commands
+pub struct RunControl {
+    // None once every sender is gone
+    commands: Option<mpsc::Receiver<ControlCommand>>,
+    // Wall-clock time between ticks at 1x, None runs the ticks back to back
+    base_interval: Option<Duration>,
+    interval: Option<Interval>,
+    pub paused: bool,
+    // Ticks left to run before pausing again
+    pub steps: u32,
+    pub speed: f32,
+    // Whether jumps are taken, live runs cannot go back
+    pub seekable: bool,
+    // Jump asked for and not made yet
+    pub jump: Option<Jump>,
+}
+
+impl RunControl {
+    // Returns the control and the sender to drive it with
+    pub fn new(base_interval: Option<Duration>) -> (RunControl, mpsc::Sender<ControlCommand>) {
+        let (tx, rx) = mpsc::channel(16);
+        let mut control = RunControl {
+            commands: Some(rx),
+            base_interval,
+            interval: None,
+            paused: false,
+            steps: 0,
+            speed: 1.0,
+            seekable: false,
+            jump: None,
+        };
+        control.reset_interval();
+        (control, tx)
+    }
+
+    pub fn apply(&mut self, command: ControlCommand) {
+        match command {
+            ControlCommand::Pause => self.paused = true,
+            ControlCommand::Resume => {
+                self.paused = false;
+                self.steps = 0;
+            },
+            ControlCommand::Step(ticks) => {
+                self.paused = true;
+                self.steps += ticks;
+            },
+            ControlCommand::SetSpeed(speed) => {
+                self.speed = speed;
+                self.reset_interval();
+            },
+            ControlCommand::Jump(jump) if self.seekable => {
+                self.paused = true;
+                self.steps = 0;
+                self.jump = Some(jump);
+            },
+            ControlCommand::Jump(_) => {},
+        }
+    }
+
+    // Wait until the next tick may run, handling commands in the meantime
+    pub async fn next_tick(&mut self) {
+        loop {
+            if self.jump.is_some() {
+                return;
+            }
+            if self.paused && self.steps > 0 {
+                self.steps -= 1;
+                return;
+            }
+
+            let received = if self.paused {
+                // Nothing runs until the next command
+                match self.commands.as_mut() {
+                    Some(commands) => commands.recv().await,
+                    None => {
+                        // Nobody left to resume, carry on
+                        self.paused = false;
+                        continue;
+                    },
+                }
+            } else {
+                match (self.interval.as_mut(), self.commands.as_mut()) {
+                    (Some(interval), Some(commands)) => tokio::select! {
+                        _ = interval.tick() => return,
+                        command = commands.recv() => command,
+                    },
+                    (Some(interval), None) => {
+                        interval.tick().await;
+                        return;
+                    },
+                    // Unpaced, only take the commands already waiting
+                    (None, Some(commands)) => match commands.try_recv() {
+                        Ok(command) => Some(command),
+                        Err(TryRecvError::Empty) => return,
+                        Err(TryRecvError::Disconnected) => None,
+                    },
+                    (None, None) => return,
+                }
+            };
+
+            match received {
+                Some(command) => self.apply(command),
+                // Every sender is gone
+                None => self.commands = None,
+            }
+        }
+    }
+
+    // Status shown above the grid
+    pub fn status(&self) -> String {
+        if self.paused {
+            "paused".to_string()
+        } else {
+            format!("{}x", self.speed)
+        }
+    }
+
+    fn reset_interval(&mut self) {
+        self.interval = self.base_interval.map(|base| {
+            let mut interval = tokio::time::interval(base.div_f32(self.speed));
+            // A slow tick pushes the next ones back instead of bursting to catch up
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            interval
+        });
+    }
+}
@@ -0,0 +1,182 @@
+// demand.rs
+use super::network::RoadNetwork;
+use super::spawn::{self, Arrival};
+use super::vehicle::VehicleType;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+// Origin-destination matrix, as CSV with a header line:
+//
+//     origin,destination,trips_per_hour,vehicle_type
+//     north,south,120,car
+//     north,"(20,20)",15,bus
+//
+// or as JSON, optionally with zones of its own:
+//
+//     { "zones": { "north": [[0, 0], [10, 0]] },
+//       "trips": [{ "origin": "north", "destination": "south", "trips_per_hour": 120 }] }
+//
+// Origins and destinations are zone names or single points written "(x,y)".
+// In CSV the quote has to follow the comma, `, "(x,y)"` splits the point.
+// Without a vehicle type the trips get a random one.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DemandFile {
+    pub zones: BTreeMap<String, Vec<(i32, i32)>>,
+    pub trips: Vec<TripRecord>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TripRecord {
+    pub origin: String,
+    pub destination: String,
+    pub trips_per_hour: f64,
+    #[serde(default)]
+    pub vehicle_type: Option<VehicleType>,
+}
+
+// The matrix resolved to points of the network, kept in snapshots
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Demand {
+    pub pairs: Vec<OdPair>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OdPair {
+    // Point indexes, trips start and end at one of them picked at random
+    pub origins: Vec<usize>,
+    pub destinations: Vec<usize>,
+    // Trips per simulated second
+    pub rate: f64,
+    pub vehicle_type: Option<VehicleType>,
+}
+
+#[derive(Debug)]
+pub enum DemandError {
+    // The file could not be read
+    Io(String),
+    // The file is not valid CSV/JSON or has unknown/mistyped columns
+    Parse(String),
+    // A zone or point that the network doesn't have, or a bad rate
+    Invalid(String),
+}
+
+impl DemandFile {
+    // .csv or .json by extension
+    pub fn load(path: &Path) -> Result<DemandFile, DemandError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| DemandError::Io(format!("{}: {}", path.display(), e)))?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => DemandFile::from_csv(&contents),
+            Some("json") => serde_json::from_str(&contents).map_err(|e| DemandError::Parse(e.to_string())),
+            _ => Err(DemandError::Parse(format!("{}: expected a .csv or .json file", path.display()))),
+        }
+    }
+
+    pub fn from_csv(contents: &str) -> Result<DemandFile, DemandError> {
+        let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(contents.as_bytes());
+        let trips = reader.deserialize()
+            .collect::<Result<Vec<TripRecord>, _>>()
+            .map_err(|e| DemandError::Parse(e.to_string()))?;
+        Ok(DemandFile {
+            zones: BTreeMap::new(),
+            trips,
+        })
+    }
+
+    // Resolve zone names and points against the network. Zones of the
+    // config come on top of those of the file.
+    pub fn resolve(
+        &self,
+        zones: &BTreeMap<String, Vec<(i32, i32)>>,
+        network: &RoadNetwork,
+    ) -> Result<Demand, DemandError> {
+        let mut all_zones = self.zones.clone();
+        all_zones.extend(zones.iter().map(|(name, points)| (name.clone(), points.clone())));
+
+        let mut pairs = Vec::new();
+        for trip in &self.trips {
+            if !trip.trips_per_hour.is_finite() || trip.trips_per_hour < 0.0 {
+                return Err(DemandError::Invalid(format!(
+                    "{} to {}: trips_per_hour must be 0 or more",
+                    trip.origin, trip.destination
+                )));
+            }
+            pairs.push(OdPair {
+                origins: points_of(&trip.origin, &all_zones, network)?,
+                destinations: points_of(&trip.destination, &all_zones, network)?,
+                rate: trip.trips_per_hour / 3600.0,
+                vehicle_type: trip.vehicle_type.clone(),
+            });
+        }
+        Ok(Demand { pairs })
+    }
+}
+
+impl Demand {
+    // Trips starting in a tick of `dt` seconds, random arrivals for every pair
+    pub fn arrivals(&self, dt: f64, rng: &mut impl Rng) -> Vec<Arrival> {
+        let mut arrivals = Vec::new();
+        for pair in &self.pairs {
+            for _ in 0..spawn::poisson(pair.rate * dt, rng) {
+                let origin = pair.origins[rng.random_range(0..pair.origins.len())];
+                // Another point of the zone if it has one, trips within a zone
+                // shouldn't end where they start
+                let mut destination = pair.destinations[rng.random_range(0..pair.destinations.len())];
+                if destination == origin && pair.destinations.len() > 1 {
+                    let others: Vec<usize> = pair.destinations.iter().copied().filter(|&d| d != origin).collect();
+                    destination = others[rng.random_range(0..others.len())];
+                }
+                arrivals.push(Arrival {
+                    start: Some(origin),
+                    destination: Some(destination),
+                    vehicle_type: pair.vehicle_type.clone(),
+                });
+            }
+        }
+        arrivals
+    }
+}
+
+// Points of a zone name or of a "(x,y)" point
+fn points_of(
+    name: &str,
+    zones: &BTreeMap<String, Vec<(i32, i32)>>,
+    network: &RoadNetwork,
+) -> Result<Vec<usize>, DemandError> {
+    let positions = match zones.get(name) {
+        Some(positions) => positions.clone(),
+        None => vec![parse_point(name)
+            .ok_or_else(|| DemandError::Invalid(format!("unknown zone `{}`", name)))?],
+    };
+    if positions.is_empty() {
+        return Err(DemandError::Invalid(format!("zone `{}` has no points", name)));
+    }
+    positions.iter()
+        .map(|&position| {
+            network.point_at(position)
+                .ok_or_else(|| DemandError::Invalid(format!("{}: no point at ({},{})", name, position.0, position.1)))
+        })
+        .collect()
+}
+
+// "(x,y)" as a position
+fn parse_point(text: &str) -> Option<(i32, i32)> {
+    let inner = text.trim().strip_prefix('(')?.strip_suffix(')')?;
+    let (x, y) = inner.split_once(',')?;
+    Some((x.trim().parse().ok()?, y.trim().parse().ok()?))
+}
+
+impl Display for DemandError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DemandError::Io(message) => write!(f, "could not read demand matrix: {}", message),
+            DemandError::Parse(message) => write!(f, "could not parse demand matrix: {}", message),
+            DemandError::Invalid(message) => write!(f, "invalid demand matrix: {}", message),
+        }
+    }
+}
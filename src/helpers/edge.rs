@@ -0,0 +1,47 @@
+// edge.rs
+use serde::{Deserialize, Serialize};
+
+// Compass direction of a road, y grows downwards like the printed grid
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Direction {
+    // Direction of travel from one position to another.
+    // Roads that are not axis aligned take their dominant axis.
+    pub fn between(from: (i32, i32), to: (i32, i32)) -> Direction {
+        let dx = to.0 - from.0;
+        let dy = to.1 - from.1;
+        if dx.abs() >= dy.abs() {
+            if dx >= 0 { Direction::East } else { Direction::West }
+        } else if dy > 0 {
+            Direction::South
+        } else {
+            Direction::North
+        }
+    }
+
+    pub fn is_horizontal(&self) -> bool {
+        matches!(self, Direction::East | Direction::West)
+    }
+}
+
+// A one-way road between two points of the network
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Edge {
+    // Indexes into RoadNetwork::points
+    pub from: usize,
+    pub to: usize,
+    // In units
+    pub length: f32,
+    pub lanes: u8,
+    // Units per second, same as vehicle speeds
+    pub speed_limit: f32,
+    pub direction: Direction,
+    // Closed roads can't be entered, routes avoid them
+    pub closed: bool,
+}
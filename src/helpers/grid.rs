@@ -1,59 +1,106 @@
 // grid.rs
+use super::config::{LightConfig, RoadConfig};
+use super::network::RoadNetwork;
 use super::point::Point;
-use super::vehicle::{Vehicle, VehicleType};
-use super::light::{LightState, TrafficLight};
+use super::routing;
+use super::traffic::{self, Cell, Occupant, OccupancyError, TrafficView};
+use super::vehicle::{Reroute, UpdateReport, Vehicle};
+use super::light::TrafficLight;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter, Result};
+use std::sync::Arc;
 use tokio::task::JoinSet;
 
+// Outcome of moving the vehicles for a tick
+pub struct VehicleUpdates {
+    pub reroutes: Vec<Reroute>,
+    // Taken off the grid, in the order they were on it
+    pub arrived: Vec<Vehicle>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct Grid {
-    pub points: Vec<Point>,
+    // Points and the roads between them.
+    // Shared with the vehicle update tasks, so it lives behind an Arc.
+    pub network: Arc<RoadNetwork>,
     pub vehicles: Vec<Vehicle>,
     pub traffic_lights: Vec<TrafficLight>,
+    // Smoothed delay factor measured on every edge (1.0 = free flow)
+    pub edge_delays: Vec<f32>,
 }
 
 impl Grid {
     pub fn new() -> Self {
         Self {
-            points: Vec::new(),
+            network: Arc::new(RoadNetwork::new()),
             vehicles: Vec::new(),
             traffic_lights: Vec::new(),
+            edge_delays: Vec::new(),
         }  
     }
 
-    pub fn generate_grid(mut self, height: i32, width: i32) -> Grid {
+    pub fn generate_grid(
+        mut self,
+        height: i32,
+        width: i32,
+        light_config: &LightConfig,
+        road_config: &RoadConfig,
+        rng: &mut impl Rng,
+    ) -> Grid {
+        let mut network = RoadNetwork::new();
+
         // Generate points for a grid (height x width cells)
         for i in 0..= height - 1 {
             for j in 0..= width - 1 {
-                let point = Point {
+                network.add_point(Point {
                     // x should be a column value 
                     x: j * 10,
                     // y should be a row value
                     y: i * 10,
-                    // Intersection appears if either x or y middle values
-                    // (Not upper bound or lower bound values)
-                    is_intersection: (j > 0 && j < width - 1) || ((i > 0 && i < height - 1)),
-                };
-                
-                // Generate trafic light for this point
-                match TrafficLight::generate_traffic_light(&point) {
-                    Ok(traffic_light) => {
-                        self.traffic_lights.push(traffic_light);
-                    },
-                    Err(_) => {
-                    }
+                    // Set once the roads are known
+                    is_intersection: false,
+                });
+            }
+        }
+
+        // Connect every point to its right and lower neighbours
+        // with a two-way road
+        for i in 0..= height - 1 {
+            for j in 0..= width - 1 {
+                let index = (i * width + j) as usize;
+                if j < width - 1 {
+                    network.add_road(index, index + 1, road_config.lanes, road_config.speed_limit);
+                }
+                if i < height - 1 {
+                    network.add_road(index, index + width as usize, road_config.lanes, road_config.speed_limit);
                 }
+            }
+        }
+
+        // Intersections are points where three or more roads meet
+        network.update_intersections();
 
-                // Add the point to grid
-                self.points.push(point);
+        // Generate trafic light for every intersection
+        for point in &network.points {
+            if let Ok(traffic_light) = TrafficLight::generate_traffic_light(point, light_config, rng) {
+                self.traffic_lights.push(traffic_light);
             }
         }
-    
-        return self; 
+
+        self.network = Arc::new(network);
+        self
     }
 
-    pub async fn update_vehicles(&mut self) {
+    // Move every vehicle by `dt` seconds, returning the reroutes that happened
+    // and the vehicles that got to their destination (or gave up)
+    pub async fn update_vehicles(&mut self, dt: f32) -> VehicleUpdates {
         // Create a collection of asynchronous tasks 
         let mut join_set = JoinSet::new();
+        // Road conditions seen by every vehicle this tick
+        let view = Arc::new(self.traffic_view());
 
         // Create a vector to store updated vehicles
         let mut updated_vehicles = Vec::with_capacity(self.vehicles.len());
@@ -62,27 +109,27 @@ impl Grid {
         }
 
         // Spawn each vehicle's update task
-        for i in 0..updated_vehicles.len() {
+        for (i, vehicle) in updated_vehicles.iter().enumerate() {
             // Move ownership of the vehicle to the task
-            let mut vehicle = updated_vehicles[i].clone();
+            let mut vehicle = vehicle.clone();
+            // Every task shares the same view of the roads
+            let view = Arc::clone(&view);
             
             join_set.spawn(async move {
-                vehicle.update().await;
-                // Return the updated vehicle
-                vehicle 
+                let report = vehicle.update(&view, dt).await;
+                // Return the updated vehicle with its index
+                (i, vehicle, report)
             });
         }
 
         // Collect updated vehicles
-        let mut i = 0;
         // Await the completion of all tasks
+        let mut results: Vec<Option<(Vehicle, UpdateReport)>> = (0..self.vehicles.len()).map(|_| None).collect();
         while let Some(result) = join_set.join_next().await {
             match result {
-                Ok(updated_vehicle) => {
-                    if i < self.vehicles.len() {
-                        self.vehicles[i] = updated_vehicle;
-                        i += 1;
-                    }
+                Ok((i, updated_vehicle, report)) => {
+                    // Tasks finish in any order, keep each result in the vehicle's slot
+                    results[i] = Some((updated_vehicle, report));
                 },
                 Err(e) => {
                     eprintln!("Vehicle update task failed: {}", e);
@@ -90,47 +137,333 @@ impl Grid {
             }
         }
 
+        // Every vehicle moved without knowing where the others went, so two of
+        // them may have turned onto the same cell. Vehicles that stayed on their
+        // edge keep their cell, the others claim theirs by priority then id and
+        // stay where they were if it is already taken.
+        let mut order: Vec<usize> = (0..results.len()).filter(|&i| results[i].is_some()).collect();
+        order.sort_by_key(|&i| {
+            let vehicle = &results[i].as_ref().unwrap().0;
+            let changed_edge = vehicle.remaining_route().first() != self.vehicles[i].remaining_route().first();
+            (changed_edge, Reverse(vehicle.priority), vehicle.id)
+        });
+
+        let mut held: HashMap<Cell, u64> = HashMap::new();
+        let mut reroutes = Vec::new();
+        for i in order {
+            let (mut vehicle, report) = results[i].take().unwrap();
+            if let Some(cell) = vehicle.cell() {
+                if held.contains_key(&cell) {
+                    // Blocked, the whole move of this tick is undone
+                    vehicle = self.vehicles[i].clone();
+                    vehicle.current_speed = 0.0;
+                    vehicle.time_on_edge += dt;
+                    vehicle.trip.stopped_time += dt;
+                    if let Some(cell) = vehicle.cell() {
+                        held.insert(cell, vehicle.id);
+                    }
+                    self.vehicles[i] = vehicle;
+                    continue;
+                }
+                held.insert(cell, vehicle.id);
+            }
+
+            for (edge, delay) in report.edge_delays {
+                self.record_edge_delay(edge, delay);
+            }
+            reroutes.extend(report.reroute);
+            self.vehicles[i] = vehicle;
+        }
+
         // Remove vehicles that have reached their destination
-        self.vehicles.retain(|vehicle| vehicle.current_position != vehicle.destination);
+        let (arrived, driving) = std::mem::take(&mut self.vehicles)
+            .into_iter()
+            .partition(|vehicle| vehicle.has_arrived());
+        self.vehicles = driving;
+
+        // Same order whichever task finished first
+        reroutes.sort_by_key(|reroute| reroute.vehicle_id);
+        VehicleUpdates { reroutes, arrived }
+    }
+
+    // Snapshot of the network, travel times and lights for the vehicles
+    pub fn traffic_view(&self) -> TrafficView {
+        let mut lights = vec![None; self.network.points.len()];
+        for light in &self.traffic_lights {
+            if let Some(point) = self.network.point_at(light.position) {
+                lights[point] = Some(light.clone());
+            }
+        }
+
+        TrafficView {
+            network: Arc::clone(&self.network),
+            travel_times: self.travel_times(),
+            lights,
+            occupancy: self.occupancy(),
+        }
+    }
+
+    // Vehicles on the roads, per edge and lane
+    pub fn occupancy(&self) -> HashMap<(usize, u8), Vec<Occupant>> {
+        let mut occupancy: HashMap<(usize, u8), Vec<Occupant>> = HashMap::new();
+        for vehicle in &self.vehicles {
+            if let Some((edge, lane, cell)) = vehicle.cell() {
+                occupancy.entry((edge, lane)).or_default().push(Occupant {
+                    cell,
+                    vehicle_id: vehicle.id,
+                    speed: vehicle.current_speed,
+                });
+            }
+        }
+        for occupants in occupancy.values_mut() {
+            occupants.sort_by_key(|occupant| (occupant.cell, occupant.vehicle_id));
+        }
+        occupancy
+    }
+
+    // Mean speed of the vehicles on the roads, in units per second
+    pub fn average_speed(&self) -> f32 {
+        if self.vehicles.is_empty() {
+            return 0.0;
+        }
+        self.vehicles.iter().map(|vehicle| vehicle.current_speed).sum::<f32>() / self.vehicles.len() as f32
+    }
+
+    // Put a new vehicle on a free lane of its first edge. Returns false,
+    // dropping the vehicle, when its cell is taken on every lane.
+    pub fn add_vehicle(&mut self, mut vehicle: Vehicle) -> bool {
+        if let Some(&edge) = vehicle.route.first() {
+            let lanes = self.network.edges[edge].lanes.max(1);
+            let cell = traffic::cell_index(vehicle.edge_progress);
+            let free = (0..lanes).find(|&lane| {
+                self.vehicles.iter().all(|other| other.cell() != Some((edge, lane, cell)))
+            });
+            match free {
+                Some(lane) => vehicle.lane = lane,
+                None => return false,
+            }
+        }
+        self.vehicles.push(vehicle);
+        true
+    }
+
+    // Invariant check: no two vehicles may hold the same cell
+    pub fn check_occupancy(&self) -> std::result::Result<(), OccupancyError> {
+        let mut held: HashMap<Cell, u64> = HashMap::new();
+        for vehicle in &self.vehicles {
+            if let Some(cell) = vehicle.cell() {
+                if let Some(&other) = held.get(&cell) {
+                    return Err(OccupancyError { cell, vehicles: (other, vehicle.id) });
+                }
+                held.insert(cell, vehicle.id);
+            }
+        }
+        Ok(())
+    }
+
+    // Live travel time of every edge: its free-flow time scaled by the
+    // measured delay, or by the delay of a vehicle still stuck on it if that
+    // is worse. Closed edges take forever.
+    pub fn travel_times(&self) -> Vec<f32> {
+        let network = &self.network;
+        let mut delays = vec![1.0_f32; network.edges.len()];
+        for (edge, delay) in self.edge_delays.iter().enumerate() {
+            delays[edge] = delays[edge].max(*delay);
+        }
+        for vehicle in &self.vehicles {
+            if let Some(&edge) = vehicle.remaining_route().first() {
+                let stuck = vehicle.time_on_edge / vehicle.free_flow_time(network, edge);
+                delays[edge] = delays[edge].max(stuck);
+            }
+        }
+
+        (0..network.edges.len())
+            .map(|edge| {
+                if network.edges[edge].closed {
+                    f32::INFINITY
+                } else {
+                    routing::free_flow_time(network, edge) * delays[edge]
+                }
+            })
+            .collect()
+    }
+
+    // Close a road (or open it again), vehicles route around closed roads
+    pub fn set_edge_closed(&mut self, edge: usize, closed: bool) {
+        // Copies the network if an update task still holds it
+        Arc::make_mut(&mut self.network).edges[edge].closed = closed;
+    }
+
+    // Fold a measured delay into the edge's moving average
+    fn record_edge_delay(&mut self, edge: usize, delay: f32) {
+        // Weight of the newest measurement
+        const SMOOTHING: f32 = 0.3;
+
+        if self.edge_delays.len() != self.network.edges.len() {
+            self.edge_delays = vec![1.0; self.network.edges.len()];
+        }
+        self.edge_delays[edge] = SMOOTHING * delay + (1.0 - SMOOTHING) * self.edge_delays[edge];
     }
 
     pub async fn update_traffic_lights(&mut self, time_passed: f32) {
         // Update all traffic lights with the elapsed time
         TrafficLight::update_traffic_lights(&mut self.traffic_lights, time_passed).await;
     }
+
+    // Remaining route of every vehicle, one line each
+    pub fn routes(&self) -> String {
+        let mut lines = String::new();
+        for vehicle in &self.vehicles {
+            let points: Vec<String> = vehicle.remaining_points(&self.network).iter()
+                .map(|(x, y)| format!("({},{})", x, y))
+                .collect();
+            lines.push_str(&format!("{}{}: {}\n", vehicle.vehicle_type.symbol(), vehicle.id, points.join(" > ")));
+        }
+        lines
+    }
+
+    // Draw networks with diagonal roads (e.g. loaded from OSM) on a scaled
+    // character map instead of the rows and columns of points
+    fn fmt_map(&self, f: &mut Formatter<'_>) -> Result {
+        // Size limit of the map in characters
+        let max_columns = 120;
+        let max_rows = 50;
+
+        let points = &self.network.points;
+        if points.is_empty() {
+            return Ok(());
+        }
+        let min_x = points.iter().map(|p| p.x).min().unwrap_or(0);
+        let max_x = points.iter().map(|p| p.x).max().unwrap_or(0);
+        let min_y = points.iter().map(|p| p.y).min().unwrap_or(0);
+        let max_y = points.iter().map(|p| p.y).max().unwrap_or(0);
+
+        // Columns per unit, at most one. Characters are about twice as
+        // tall as they are wide, so rows get half of that.
+        let mut scale: f32 = 1.0;
+        if max_x > min_x {
+            scale = scale.min((max_columns - 1) as f32 / (max_x - min_x) as f32);
+        }
+        if max_y > min_y {
+            scale = scale.min(2.0 * (max_rows - 1) as f32 / (max_y - min_y) as f32);
+        }
+        let cell = |x: f32, y: f32| -> (usize, usize) {
+            (
+                ((y - min_y as f32) * scale / 2.0).round() as usize,
+                ((x - min_x as f32) * scale).round() as usize,
+            )
+        };
+
+        let (rows, columns) = cell(max_x as f32, max_y as f32);
+        let mut map = vec![vec![" "; columns + 1]; rows + 1];
+
+        // Roads first...
+        for edge in &self.network.edges {
+            let (ax, ay) = self.network.position(edge.from);
+            let (bx, by) = self.network.position(edge.to);
+            let (start, end) = (cell(ax as f32, ay as f32), cell(bx as f32, by as f32));
+            let steps = start.0.abs_diff(end.0).max(start.1.abs_diff(end.1)).max(1);
+            for step in 0..=steps {
+                let t = step as f32 / steps as f32;
+                let (row, column) = cell(ax as f32 + (bx - ax) as f32 * t, ay as f32 + (by - ay) as f32 * t);
+                map[row][column] = ".";
+            }
+        }
+
+        // ...then vehicles...
+        let vehicle_symbols: Vec<String> = self.vehicles.iter()
+            .map(|vehicle| vehicle.vehicle_type.symbol().to_string())
+            .collect();
+        for (vehicle, symbol) in self.vehicles.iter().zip(&vehicle_symbols) {
+            let (row, column) = cell(vehicle.current_position.0, vehicle.current_position.1);
+            if row < map.len() && column < map[row].len() {
+                map[row][column] = symbol;
+            }
+        }
+
+        // ...and points with their lights on top
+        for point in points {
+            let (row, column) = cell(point.x as f32, point.y as f32);
+            let light = self.traffic_lights.iter()
+                .find(|light| light.position == (point.x, point.y));
+            map[row][column] = match light {
+                Some(light) => light.light_state.symbol(),
+                None if point.is_intersection => "X",
+                None => "O",
+            };
+        }
+
+        for row in map {
+            writeln!(f, "{}", row.concat().trim_end())?;
+        }
+        Ok(())
+    }
+
+    // Symbol of the first vehicle drawn in one of the 9 slots
+    // of the road between two points
+    fn vehicle_symbol_between(&self, from: (i32, i32), to: (i32, i32), slot: i32) -> Option<char> {
+        // Roads drawn by Display are either horizontal or vertical
+        let span = (to.0 - from.0) + (to.1 - from.1);
+
+        for vehicle in &self.vehicles {
+            let (x, y) = vehicle.rounded_position();
+            let offset = if from.1 == to.1 && y == from.1 {
+                x - from.0
+            } else if from.0 == to.0 && x == from.0 {
+                y - from.1
+            } else {
+                continue;
+            };
+            // Vehicles on the points themselves are not drawn
+            if offset <= 0 || offset >= span {
+                continue;
+            }
+
+            // Scale the offset to the slots, with 10 units between points
+            // every unit is one slot
+            let vehicle_slot = ((offset * 10) as f32 / span as f32).round() as i32;
+            if vehicle_slot.clamp(1, 9) == slot {
+                return Some(vehicle.vehicle_type.symbol());
+            }
+        }
+        None
+    }
 }
 
 impl Display for Grid {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-        // Group points by rows (x-coordinate)
+        let network = &self.network;
+
+        // Rows and columns only work for horizontal and vertical roads
+        if !network.is_axis_aligned() {
+            return self.fmt_map(f);
+        }
+
+        // Group point indexes by rows (y-coordinate)
         let mut rows = std::collections::BTreeMap::new();
-        for point in &self.points {
-            // Group by y not x
-            rows.entry(point.y).or_insert(Vec::new()).push(point);
+        for (index, point) in network.points.iter().enumerate() {
+            rows.entry(point.y).or_insert(Vec::new()).push(index);
         }
+        let row_values: Vec<i32> = rows.keys().copied().collect();
 
         // Iterate through rows (y-values)
-        for (y, points) in &rows {
+        for (row, y) in row_values.iter().enumerate() {
             // Sort points in a row by X-coordinate 
             // To print columns left-to-right
-            // Clone because points are reference and can't be borrowed as mut
-            let mut sorted_points = points.clone();
-            sorted_points.sort_by_key(|p| p.x);
-            // points.sort_by_key(|p| p.x);
+            let mut sorted_points = rows[y].clone();
+            sorted_points.sort_by_key(|&p| network.points[p].x);
 
             // Print points with horizontal connections
-            for (i, point) in sorted_points.iter().enumerate() {
+            for (i, &index) in sorted_points.iter().enumerate() {
+                let point = &network.points[index];
+
                 // Check if there's a traffic light at this point
                 let traffic_light = self.traffic_lights.iter()
                 .find(|light| light.position.0 == point.x && light.position.1 == point.y);
                 
                 if let Some(light) = traffic_light {
                     // Display the point with traffic light information
-                    let light_symbol = match light.light_state {
-                        LightState::Green => "\x1B[32mG\x1B[0m",  // Green text
-                        LightState::Yellow => "\x1B[33mY\x1B[0m", // Yellow text
-                        LightState::Red => "\x1B[31mR\x1B[0m",    // Red text
-                    };
+                    let light_symbol = light.light_state.symbol();
                     
                     // Format coordinates the same way as in Point::fmt
                     let coords = if point.x > 9 && point.y > 9 {
@@ -151,58 +484,52 @@ impl Display for Grid {
                 
                 // Write the Horizontal connections
                 if i < sorted_points.len() - 1 {
+                    let next = sorted_points[i + 1];
+                    // Only draw the road if the graph has one
+                    let connected = network.is_connected(index, next);
+
                     // 9 dots between horizontal points
-                    for j in 1..=9 {
-                        // Intermediate x between consecutive points
-                        let current_x = point.x + j;
-                        let current_y = *y;
-                        let mut vehicle_symbol = None;
-
-                        for vehicle in &self.vehicles {
-                            // Current_position is a tupple, so we acces by index
-                            if vehicle.current_position.0 == current_x 
-                            && vehicle.current_position.1 == current_y {
-                                vehicle_symbol = Some(match vehicle.vehicle_type {
-                                    VehicleType::Car => 'C',
-                                    VehicleType::Bus => 'B',
-                                    VehicleType::Emergency => 'E',
-                                });
-                                break;
-                            }
-                        }
-                        write!(f, "{}", vehicle_symbol.unwrap_or('.'))?;
+                    for slot in 1..=9 {
+                        let symbol = if connected {
+                            self.vehicle_symbol_between(network.position(index), network.position(next), slot)
+                            .unwrap_or('.')
+                        } else {
+                            ' '
+                        };
+                        write!(f, "{}", symbol)?;
                     }
                 }
             }
             writeln!(f)?;
 
             // Print vertical lines below (except last row)
-            if *y < (rows.len() as i32 - 1) * 10 {
+            if let Some(next_y) = row_values.get(row + 1) {
+                // Point right below each point of this row, if a road leads there
+                let below: Vec<Option<usize>> = sorted_points.iter()
+                    .map(|&index| {
+                        rows[next_y].iter().copied().find(|&other| {
+                            network.points[other].x == network.points[index].x
+                                && network.is_connected(index, other)
+                        })
+                    })
+                    .collect();
+
                 for line in 1..=9 {
-                    for point in &sorted_points {
-                        let current_y = y + line;
-                        let mut vehicle_symbol: Option<char> = None;
-
-                        for vehicle in &self.vehicles {
-                            // Current_position is a tupple, so we acces by index
-                            if vehicle.current_position.0 == point.x 
-                            && vehicle.current_position.1 == current_y {
-                                vehicle_symbol = Some(match vehicle.vehicle_type {
-                                    VehicleType::Car => 'C',
-                                    VehicleType::Bus => 'B',
-                                    VehicleType::Emergency => 'E',
-                                });
-                                break;
-                            }
-                        }
+                    for (&index, lower) in sorted_points.iter().zip(&below) {
+                        let symbol = match lower {
+                            Some(lower) => self
+                                .vehicle_symbol_between(network.position(index), network.position(*lower), line)
+                                .unwrap_or('.'),
+                            None => ' ',
+                        };
                         // Get the string representation of the point 
-                        let point_string = format!("{}", point); 
+                        let point_string = format!("{}", network.points[index]); 
                         // Adjust spacing based on point's width
                         // +10 because of the number of dots between horizontal points
                         // -2 to adjust spacing.
                         let spacing = " ".repeat(point_string.len() + 10 - 2); 
                         // Vertical line + spacing
-                        write!(f, "{}{}", vehicle_symbol.unwrap_or('.'), spacing)?;
+                        write!(f, "{}{}", symbol, spacing)?;
                     }
                     writeln!(f)?;
                 }
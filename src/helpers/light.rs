@@ -1,16 +1,42 @@
 //light.rs
+use super::config::LightConfig;
+use super::edge::Direction;
 use super::point::Point;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 use tokio::task::JoinSet;
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum LightState {
     Green,
     Yellow,
     Red,
 }
 
-#[derive(Clone)]
+impl LightState {
+    // Colored letter used when printing the grid
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            LightState::Green => "\x1B[32mG\x1B[0m",  // Green text
+            LightState::Yellow => "\x1B[33mY\x1B[0m", // Yellow text
+            LightState::Red => "\x1B[31mR\x1B[0m",    // Red text
+        }
+    }
+}
+
+// Timing of one light: how long each phase lasts and how far into its
+// cycle (which starts with green) the light is when the simulation starts.
+// Offsets between neighbouring lights give green waves.
+#[derive(Clone, Debug)]
+pub struct SignalPlan {
+    // In seconds:
+    pub green_duration: f32,
+    pub yellow_duration: f32,
+    pub red_duration: f32,
+    pub offset: f32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TrafficLight {
     pub light_state: LightState,
     pub position: (i32, i32),
@@ -26,35 +52,82 @@ impl TrafficLight {
     pub fn new(
         light_state: LightState,
         position: (i32, i32),
+        durations: &LightConfig,
     ) -> TrafficLight {
         TrafficLight { 
             light_state, 
             position, 
             // In seconds:
             time_in_state: 0.0,
-            green_duration: 3.0,
-            yellow_duration: 1.0,  
-            red_duration: 2.0, 
+            green_duration: durations.green_duration,
+            yellow_duration: durations.yellow_duration,  
+            red_duration: durations.red_duration, 
         }
     }
 
-    pub fn generate_traffic_light(point: &Point) -> Result<TrafficLight, &'static str> {
+    pub fn generate_traffic_light(
+        point: &Point,
+        durations: &LightConfig,
+        rng: &mut impl Rng,
+    ) -> Result<TrafficLight, &'static str> {
         if point.is_intersection {
-            // Generate random state for initial value
-            let light_state = match rand::rng().random_range(1..=3) {
-                1 => LightState::Green,
-                2 => LightState::Yellow,
-                3 => LightState::Red,
-                _ => unreachable!(), 
-            };
-            
-            // Create the TrafficLight with properties
-            Ok(TrafficLight::new(light_state, (point.x, point.y)))
+            Ok(TrafficLight::with_random_state((point.x, point.y), durations, rng))
         } else {
             Err("Given point is not an intersection")
         }
     }
 
+    // Create a light at any position (e.g. a signalized node of a loaded network)
+    pub fn with_random_state(position: (i32, i32), durations: &LightConfig, rng: &mut impl Rng) -> TrafficLight {
+        // Generate random state for initial value
+        let light_state = match rng.random_range(1..=3) {
+            1 => LightState::Green,
+            2 => LightState::Yellow,
+            3 => LightState::Red,
+            _ => unreachable!(), 
+        };
+
+        // Create the TrafficLight with properties
+        TrafficLight::new(light_state, position, durations)
+    }
+
+    // Switch to a signal plan, starting at its offset into the cycle
+    pub fn apply_plan(&mut self, plan: &SignalPlan) {
+        self.green_duration = plan.green_duration;
+        self.yellow_duration = plan.yellow_duration;
+        self.red_duration = plan.red_duration;
+
+        let cycle = plan.green_duration + plan.yellow_duration + plan.red_duration;
+        let mut time = plan.offset.rem_euclid(cycle);
+        let phases = [
+            (LightState::Green, plan.green_duration),
+            (LightState::Yellow, plan.yellow_duration),
+            (LightState::Red, plan.red_duration),
+        ];
+        for (state, duration) in phases {
+            self.light_state = state;
+            self.time_in_state = time;
+            if time < duration {
+                break;
+            }
+            time -= duration;
+        }
+    }
+
+    // State seen by vehicles arriving with the given heading.
+    // The light's own cycle controls east-west traffic, north-south
+    // traffic gets green while it is red, turning yellow before it ends.
+    pub fn state_for(&self, heading: Direction) -> LightState {
+        if heading.is_horizontal() {
+            return self.light_state.clone();
+        }
+        match self.light_state {
+            LightState::Green | LightState::Yellow => LightState::Red,
+            LightState::Red if self.time_in_state < self.red_duration - self.yellow_duration => LightState::Green,
+            LightState::Red => LightState::Yellow,
+        }
+    }
+
     // Get current state duration based on light state
     fn get_current_state_duration(&self) -> f32 {
         match self.light_state {
@@ -78,17 +151,16 @@ impl TrafficLight {
         // Add the elapsed time to our time in current state
         self.time_in_state += time_passed;
         
-        // Check if it's time to change state
-        if self.time_in_state >= self.get_current_state_duration() {
-            // Change to next state
+        // Change state for every phase that ended. The time past the end
+        // carries over, so phases last the same whatever the tick length.
+        while self.time_in_state >= self.get_current_state_duration() {
+            self.time_in_state -= self.get_current_state_duration();
             self.light_state = self.next_state();
-            // Reset the timer
-            self.time_in_state = 0.0;
         }
     }
 
     // Update all traffic lights in the grid's vector
-    pub async fn update_traffic_lights(traffic_lights: &mut Vec<TrafficLight>, time_passed: f32) {
+    pub async fn update_traffic_lights(traffic_lights: &mut [TrafficLight], time_passed: f32) {
         // Create a collection of asynchronous tasks 
         let mut join_set = JoinSet::new();
 
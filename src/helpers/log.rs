@@ -0,0 +1,57 @@
+// log.rs
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+// How much the engine writes besides the grid and the summary
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    // Only failures
+    Error,
+    // Also problems the run carries on from, e.g. skipped scenario events
+    Warn,
+    // Also notes about files written
+    #[default]
+    Info,
+    // Also every message sent to the analyzer
+    Debug,
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "error" => Ok(LogLevel::Error),
+            "warn" => Ok(LogLevel::Warn),
+            "info" => Ok(LogLevel::Info),
+            "debug" => Ok(LogLevel::Debug),
+            _ => Err(format!("unknown log level `{}`, expected error, warn, info or debug", value)),
+        }
+    }
+}
+
+// Shared by the whole process, set once from the config
+static LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+
+pub fn set_level(level: LogLevel) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn enabled(level: LogLevel) -> bool {
+    level as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+pub fn warn(message: impl Display) {
+    if enabled(LogLevel::Warn) {
+        eprintln!("{}", message);
+    }
+}
+
+pub fn info(message: impl Display) {
+    if enabled(LogLevel::Info) {
+        eprintln!("{}", message);
+    }
+}
@@ -1,11 +1,53 @@
 //message.rs
 use serde::{Serialize, Deserialize};
+use super::vehicle::{RerouteReason, VehicleType};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum SimulationMessage {
     GridUpdate { 
         tick: u64,
+        // Simulated seconds since the start
+        time: f64,
         vehicle_count: usize, 
         light_count: usize,
+        // Mean speed of the vehicles on the roads, units per second
+        average_speed: f32,
+        // Waiting outside an open network for room to enter
+        queued: usize,
+    },
+    // Sent when a vehicle enters the grid with its planned path
+    RoutePlanned {
+        tick: u64,
+        vehicle_id: u64,
+        route: Vec<(i32, i32)>,
+    },
+    // Sent when a vehicle switches to a faster route mid-trip,
+    // with the expected remaining travel time of both routes
+    Rerouted {
+        tick: u64,
+        vehicle_id: u64,
+        reason: RerouteReason,
+        old_travel_time: f32,
+        new_travel_time: f32,
+    },
+    // Sent when a vehicle leaves the grid, at its destination or after giving
+    // up on the way. Times are simulated seconds, the distance is in units.
+    TripCompleted {
+        // Tick it left in
+        tick: u64,
+        vehicle_id: u64,
+        vehicle_type: VehicleType,
+        // Gave up, no route to the destination
+        abandoned: bool,
+        // Tick it entered the grid in
+        spawn_tick: u64,
+        distance: f32,
+        travel_time: f32,
+        // Spent standing still, at lights or in queues
+        stopped_time: f32,
+        // What the trip takes alone on the roads at the vehicle's top speed
+        free_flow_time: f32,
+        // travel_time - free_flow_time, never below 0
+        delay: f32,
     },
 }
\ No newline at end of file
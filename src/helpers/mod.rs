@@ -1,7 +1,19 @@
-pub mod variables;
 pub mod analyzer;
+pub mod boundary;
+pub mod clock;
+pub mod config;
+pub mod control;
+pub mod demand;
+pub mod edge;
 pub mod grid;
 pub mod light;
+pub mod log;
 pub mod message;
+pub mod network;
+pub mod network_file;
+pub mod osm;
 pub mod point;
+pub mod routing;
+pub mod spawn;
+pub mod traffic;
 pub mod vehicle;
\ No newline at end of file
@@ -0,0 +1,165 @@
+// network.rs
+use super::edge::{Direction, Edge};
+use super::point::Point;
+use serde::{Deserialize, Serialize};
+
+// Directed road graph: the points are the nodes, edges are one-way roads.
+// Two-way streets are stored as a pair of opposite edges.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RoadNetwork {
+    pub points: Vec<Point>,
+    pub edges: Vec<Edge>,
+    // Outgoing and incoming edge indexes of every point
+    outgoing: Vec<Vec<usize>>,
+    incoming: Vec<Vec<usize>>,
+}
+
+impl RoadNetwork {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Add a node and return its index
+    pub fn add_point(&mut self, point: Point) -> usize {
+        self.points.push(point);
+        self.outgoing.push(Vec::new());
+        self.incoming.push(Vec::new());
+        self.points.len() - 1
+    }
+
+    // Add a one-way road and return its index.
+    // Length and direction are derived from the point coordinates.
+    pub fn add_edge(&mut self, from: usize, to: usize, lanes: u8, speed_limit: f32) -> usize {
+        let start = self.position(from);
+        let end = self.position(to);
+        let dx = (end.0 - start.0) as f32;
+        let dy = (end.1 - start.1) as f32;
+
+        self.edges.push(Edge {
+            from,
+            to,
+            length: (dx * dx + dy * dy).sqrt(),
+            lanes,
+            speed_limit,
+            direction: Direction::between(start, end),
+            closed: false,
+        });
+        let index = self.edges.len() - 1;
+        self.outgoing[from].push(index);
+        self.incoming[to].push(index);
+        index
+    }
+
+    // Add a two-way road as two opposite edges
+    pub fn add_road(&mut self, a: usize, b: usize, lanes: u8, speed_limit: f32) {
+        self.add_edge(a, b, lanes, speed_limit);
+        self.add_edge(b, a, lanes, speed_limit);
+    }
+
+    pub fn position(&self, point: usize) -> (i32, i32) {
+        (self.points[point].x, self.points[point].y)
+    }
+
+    // Index of the point at the given coordinates
+    pub fn point_at(&self, position: (i32, i32)) -> Option<usize> {
+        self.points.iter().position(|p| (p.x, p.y) == position)
+    }
+
+    pub fn outgoing_edges(&self, point: usize) -> &[usize] {
+        &self.outgoing[point]
+    }
+
+    pub fn incoming_edges(&self, point: usize) -> &[usize] {
+        &self.incoming[point]
+    }
+
+    // Edge going from one point straight to another
+    pub fn edge_between(&self, from: usize, to: usize) -> Option<usize> {
+        self.outgoing[from].iter().copied().find(|&e| self.edges[e].to == to)
+    }
+
+    // Whether two points are connected by a road in either direction
+    pub fn is_connected(&self, a: usize, b: usize) -> bool {
+        self.edge_between(a, b).is_some() || self.edge_between(b, a).is_some()
+    }
+
+    // Points reachable by a single edge
+    pub fn successors(&self, point: usize) -> Vec<usize> {
+        self.outgoing[point].iter().map(|&e| self.edges[e].to).collect()
+    }
+
+    // Distinct points connected to this one in either direction
+    pub fn neighbors(&self, point: usize) -> Vec<usize> {
+        let mut neighbors: Vec<usize> = self.outgoing[point].iter()
+            .map(|&e| self.edges[e].to)
+            .chain(self.incoming[point].iter().map(|&e| self.edges[e].from))
+            .collect();
+        neighbors.sort_unstable();
+        neighbors.dedup();
+        neighbors
+    }
+
+    // Edges whose segment contains the given position (both directions of
+    // a two-way road match). Points themselves match every adjacent edge.
+    pub fn edges_at(&self, position: (i32, i32)) -> Vec<usize> {
+        (0..self.edges.len())
+            .filter(|&e| self.contains(e, position))
+            .collect()
+    }
+
+    // Whether a position lies on the segment of an edge
+    pub fn contains(&self, edge: usize, position: (i32, i32)) -> bool {
+        let start = self.position(self.edges[edge].from);
+        let end = self.position(self.edges[edge].to);
+
+        // Collinear with the segment...
+        let cross = (end.0 - start.0) as i64 * (position.1 - start.1) as i64
+            - (end.1 - start.1) as i64 * (position.0 - start.0) as i64;
+        // ...and inside its bounding box
+        cross == 0
+            && position.0 >= start.0.min(end.0) && position.0 <= start.0.max(end.0)
+            && position.1 >= start.1.min(end.1) && position.1 <= start.1.max(end.1)
+    }
+
+    // Edges whose segment holds an exact position, with the distance
+    // from their start. Used to place vehicles standing between points.
+    pub fn locate(&self, position: (f32, f32)) -> Vec<(usize, f32)> {
+        // Slack for positions interpolated along diagonal roads
+        const TOLERANCE: f32 = 1e-3;
+
+        (0..self.edges.len())
+            .filter_map(|e| {
+                let edge = &self.edges[e];
+                let (ax, ay) = self.position(edge.from);
+                let (bx, by) = self.position(edge.to);
+                let (dx, dy) = ((bx - ax) as f32, (by - ay) as f32);
+                let (px, py) = (position.0 - ax as f32, position.1 - ay as f32);
+                if edge.length == 0.0 {
+                    return None;
+                }
+                // Distance off the line and along it
+                let off = (dx * py - dy * px).abs() / edge.length;
+                let along = (dx * px + dy * py) / edge.length;
+                if off > TOLERANCE || along < -TOLERANCE || along > edge.length + TOLERANCE {
+                    return None;
+                }
+                Some((e, along.clamp(0.0, edge.length)))
+            })
+            .collect()
+    }
+
+    // Whether every road is horizontal or vertical
+    pub fn is_axis_aligned(&self) -> bool {
+        self.edges.iter().all(|edge| {
+            let (start, end) = (self.position(edge.from), self.position(edge.to));
+            start.0 == end.0 || start.1 == end.1
+        })
+    }
+
+    // A point is an intersection when three or more roads meet there
+    pub fn update_intersections(&mut self) {
+        for i in 0..self.points.len() {
+            self.points[i].is_intersection = self.neighbors(i).len() >= 3;
+        }
+    }
+}
@@ -0,0 +1,220 @@
+// network_file.rs
+use super::config::{LightConfig, RoadConfig};
+use super::grid::Grid;
+use super::light::TrafficLight;
+use super::network::RoadNetwork;
+use super::point::Point;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::sync::Arc;
+
+// JSON description of a road network:
+// {
+//   "nodes": [{ "id": 0, "x": 0, "y": 0, "signalized": false }, ...],
+//   "edges": [{ "from": 0, "to": 1, "lanes": 2, "speed_limit": 3.0, "one_way": false }, ...]
+// }
+// Lanes and speed limit default to the [roads] config, edges are two-way by default.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkFile {
+    pub nodes: Vec<NodeRecord>,
+    pub edges: Vec<EdgeRecord>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NodeRecord {
+    // Any unique number, edges refer to nodes by it
+    pub id: u64,
+    pub x: i32,
+    pub y: i32,
+    // Whether a traffic light controls this node
+    #[serde(default)]
+    pub signalized: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EdgeRecord {
+    pub from: u64,
+    pub to: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lanes: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speed_limit: Option<f32>,
+    #[serde(default)]
+    pub one_way: bool,
+}
+
+#[derive(Debug)]
+pub enum NetworkError {
+    // The file could not be read or written
+    Io(String),
+    // The file is not valid JSON or has unknown/mistyped keys
+    Parse(String),
+    // The JSON is well formed but doesn't describe a valid network
+    Invalid(String),
+}
+
+impl NetworkFile {
+    pub fn from_json(contents: &str) -> Result<NetworkFile, NetworkError> {
+        serde_json::from_str(contents).map_err(|e| NetworkError::Parse(e.to_string()))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Network file serialization failed")
+    }
+
+    // Build the road graph and the lights of the signalized nodes
+    pub fn build(
+        &self,
+        light_config: &LightConfig,
+        road_config: &RoadConfig,
+        rng: &mut impl Rng,
+    ) -> Result<(RoadNetwork, Vec<TrafficLight>), NetworkError> {
+        let mut network = RoadNetwork::new();
+        let mut lights = Vec::new();
+        // Node id -> point index
+        let mut indexes = HashMap::new();
+
+        if self.nodes.is_empty() {
+            return Err(NetworkError::Invalid("nodes: the network needs at least one node".to_string()));
+        }
+
+        for node in &self.nodes {
+            if network.point_at((node.x, node.y)).is_some() {
+                return Err(NetworkError::Invalid(format!(
+                    "node {}: another node is already at ({}, {})",
+                    node.id, node.x, node.y
+                )));
+            }
+            let index = network.add_point(Point {
+                x: node.x,
+                y: node.y,
+                // Set once the roads are known
+                is_intersection: false,
+            });
+            if indexes.insert(node.id, index).is_some() {
+                return Err(NetworkError::Invalid(format!("node {}: duplicate id", node.id)));
+            }
+            if node.signalized {
+                lights.push(TrafficLight::with_random_state((node.x, node.y), light_config, rng));
+            }
+        }
+
+        for (i, edge) in self.edges.iter().enumerate() {
+            let from = *indexes.get(&edge.from).ok_or_else(|| {
+                NetworkError::Invalid(format!("edges[{}].from: unknown node {}", i, edge.from))
+            })?;
+            let to = *indexes.get(&edge.to).ok_or_else(|| {
+                NetworkError::Invalid(format!("edges[{}].to: unknown node {}", i, edge.to))
+            })?;
+            if from == to {
+                return Err(NetworkError::Invalid(format!("edges[{}]: starts and ends at node {}", i, edge.from)));
+            }
+
+            let lanes = edge.lanes.unwrap_or(road_config.lanes);
+            if lanes < 1 {
+                return Err(NetworkError::Invalid(format!("edges[{}].lanes: must be at least 1", i)));
+            }
+            let speed_limit = edge.speed_limit.unwrap_or(road_config.speed_limit);
+            if speed_limit.is_nan() || speed_limit <= 0.0 {
+                return Err(NetworkError::Invalid(format!("edges[{}].speed_limit: must be positive", i)));
+            }
+
+            if edge.one_way {
+                network.add_edge(from, to, lanes, speed_limit);
+            } else {
+                network.add_road(from, to, lanes, speed_limit);
+            }
+        }
+
+        // Intersections are points where three or more roads meet
+        network.update_intersections();
+        Ok((network, lights))
+    }
+
+    // Describe an existing grid, the point indexes become the node ids
+    pub fn from_grid(grid: &Grid) -> NetworkFile {
+        let network = &grid.network;
+
+        let nodes = network.points.iter().enumerate()
+            .map(|(index, point)| NodeRecord {
+                id: index as u64,
+                x: point.x,
+                y: point.y,
+                signalized: grid.traffic_lights.iter().any(|light| light.position == (point.x, point.y)),
+            })
+            .collect();
+
+        let mut edges = Vec::new();
+        // Opposite edge already written as part of a two-way road
+        let mut written = vec![false; network.edges.len()];
+        for (index, edge) in network.edges.iter().enumerate() {
+            if written[index] {
+                continue;
+            }
+            written[index] = true;
+
+            // Merge with the opposite edge when both have the same properties
+            let opposite = network.edge_between(edge.to, edge.from).filter(|&other| {
+                !written[other]
+                    && network.edges[other].lanes == edge.lanes
+                    && network.edges[other].speed_limit == edge.speed_limit
+            });
+            if let Some(other) = opposite {
+                written[other] = true;
+            }
+
+            edges.push(EdgeRecord {
+                from: edge.from as u64,
+                to: edge.to as u64,
+                lanes: Some(edge.lanes),
+                speed_limit: Some(edge.speed_limit),
+                one_way: opposite.is_none(),
+            });
+        }
+
+        NetworkFile { nodes, edges }
+    }
+}
+
+impl Grid {
+    // Build a grid from a JSON network file instead of a generated lattice
+    pub fn load_network(
+        path: &Path,
+        light_config: &LightConfig,
+        road_config: &RoadConfig,
+        rng: &mut impl Rng,
+    ) -> Result<Grid, NetworkError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| NetworkError::Io(format!("{}: {}", path.display(), e)))?;
+        let (network, traffic_lights) = NetworkFile::from_json(&contents)?.build(light_config, road_config, rng)?;
+
+        let mut grid = Grid::new();
+        grid.network = Arc::new(network);
+        grid.traffic_lights = traffic_lights;
+        Ok(grid)
+    }
+
+    // Save the grid's network so it can be edited and loaded again
+    pub fn save_network(&self, path: &Path) -> Result<(), NetworkError> {
+        std::fs::write(path, NetworkFile::from_grid(self).to_json())
+            .map_err(|e| NetworkError::Io(format!("{}: {}", path.display(), e)))
+    }
+}
+
+impl Display for NetworkError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NetworkError::Io(message) => write!(f, "could not access network file: {}", message),
+            NetworkError::Parse(message) => write!(f, "could not parse network file: {}", message),
+            NetworkError::Invalid(message) => write!(f, "invalid network: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for NetworkError {}
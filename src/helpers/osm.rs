@@ -0,0 +1,259 @@
+// osm.rs
+use super::config::{LightConfig, RoadConfig};
+use super::grid::Grid;
+use super::light::TrafficLight;
+use super::network::RoadNetwork;
+use super::network_file::NetworkError;
+use super::point::Point;
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+
+// Mean earth radius in meters, for projecting lat/lon
+const EARTH_RADIUS: f64 = 6_371_000.0;
+
+// Values of the highway=* tag that vehicles can drive on
+const DRIVABLE_HIGHWAYS: [&str; 16] = [
+    "motorway", "motorway_link", "trunk", "trunk_link",
+    "primary", "primary_link", "secondary", "secondary_link",
+    "tertiary", "tertiary_link", "unclassified", "residential",
+    "living_street", "service", "road", "busway",
+];
+
+struct OsmNode {
+    lat: f64,
+    lon: f64,
+    is_signal: bool,
+}
+
+struct OsmWay {
+    nodes: Vec<i64>,
+    // 1 = one-way along the node order, -1 = against it, 0 = two-way
+    oneway: i8,
+    // km/h
+    maxspeed: Option<f32>,
+    lanes: Option<u8>,
+}
+
+impl Grid {
+    // Build a grid from an OpenStreetMap XML extract.
+    // Junctions and signals become points, highway ways become edges between
+    // them and traffic_signals nodes get a traffic light.
+    pub fn load_osm(
+        path: &Path,
+        light_config: &LightConfig,
+        road_config: &RoadConfig,
+        meters_per_unit: f32,
+        rng: &mut impl Rng,
+    ) -> Result<Grid, NetworkError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| NetworkError::Io(format!("{}: {}", path.display(), e)))?;
+        let (network, signals) = parse_osm(&contents, road_config, meters_per_unit)?;
+
+        let mut grid = Grid::new();
+        grid.traffic_lights = signals.iter()
+            .map(|&point| TrafficLight::with_random_state(network.position(point), light_config, &mut *rng))
+            .collect();
+        grid.network = Arc::new(network);
+        Ok(grid)
+    }
+}
+
+// Turn the XML into a road network, also returning the signalized points
+pub fn parse_osm(
+    contents: &str,
+    road_config: &RoadConfig,
+    meters_per_unit: f32,
+) -> Result<(RoadNetwork, Vec<usize>), NetworkError> {
+    let document = roxmltree::Document::parse(contents)
+        .map_err(|e| NetworkError::Parse(e.to_string()))?;
+
+    let mut nodes: HashMap<i64, OsmNode> = HashMap::new();
+    let mut ways = Vec::new();
+    for element in document.root_element().children().filter(|n| n.is_element()) {
+        match element.tag_name().name() {
+            "node" => {
+                let id = attribute(&element, "id")?;
+                let lat = attribute(&element, "lat")?;
+                let lon = attribute(&element, "lon")?;
+                let is_signal = tag(&element, "highway") == Some("traffic_signals");
+                nodes.insert(id, OsmNode { lat, lon, is_signal });
+            },
+            "way" => {
+                let highway = tag(&element, "highway").unwrap_or_default();
+                if !DRIVABLE_HIGHWAYS.contains(&highway) {
+                    continue;
+                }
+                let mut refs = Vec::new();
+                for nd in element.children().filter(|n| n.has_tag_name("nd")) {
+                    refs.push(attribute(&nd, "ref")?);
+                }
+                ways.push(OsmWay {
+                    nodes: refs,
+                    oneway: oneway(&element, highway),
+                    maxspeed: tag(&element, "maxspeed").and_then(parse_maxspeed),
+                    lanes: tag(&element, "lanes").and_then(|lanes| lanes.parse().ok()),
+                });
+            },
+            _ => {}
+        }
+    }
+
+    // Drop references to nodes missing from the extract (clipped ways)
+    for way in &mut ways {
+        way.nodes.retain(|id| nodes.contains_key(id));
+    }
+    ways.retain(|way| way.nodes.len() >= 2);
+    if ways.is_empty() {
+        return Err(NetworkError::Invalid("the extract has no drivable highway ways".to_string()));
+    }
+
+    // Junctions are way ends, nodes shared by several ways and signals
+    let mut use_count: HashMap<i64, usize> = HashMap::new();
+    for way in &ways {
+        for id in &way.nodes {
+            *use_count.entry(*id).or_insert(0) += 1;
+        }
+    }
+    let mut junctions = HashSet::new();
+    for way in &ways {
+        junctions.insert(way.nodes[0]);
+        junctions.insert(way.nodes[way.nodes.len() - 1]);
+        for id in &way.nodes {
+            if use_count[id] > 1 || nodes[id].is_signal {
+                junctions.insert(*id);
+            }
+        }
+    }
+
+    // Equirectangular projection around the top left corner of the
+    // used nodes, y grows southwards like the printed grid
+    let used: Vec<&OsmNode> = use_count.keys().map(|id| &nodes[id]).collect();
+    let min_lon = used.iter().map(|n| n.lon).fold(f64::INFINITY, f64::min);
+    let max_lat = used.iter().map(|n| n.lat).fold(f64::NEG_INFINITY, f64::max);
+    let mean_lat = used.iter().map(|n| n.lat).sum::<f64>() / used.len() as f64;
+    let project = |node: &OsmNode| -> (f64, f64) {
+        let x = (node.lon - min_lon).to_radians() * mean_lat.to_radians().cos() * EARTH_RADIUS;
+        let y = (max_lat - node.lat).to_radians() * EARTH_RADIUS;
+        (x / meters_per_unit as f64, y / meters_per_unit as f64)
+    };
+
+    let mut network = RoadNetwork::new();
+    let mut signals = Vec::new();
+    // OSM node id -> point index
+    let mut points = HashMap::new();
+    // Sorted so the point order doesn't depend on hashing
+    let mut sorted_junctions: Vec<i64> = junctions.into_iter().collect();
+    sorted_junctions.sort_unstable();
+    for id in sorted_junctions {
+        let (x, y) = project(&nodes[&id]);
+        let position = (x.round() as i32, y.round() as i32);
+        // Junctions closer than one unit collapse into the same point
+        let index = match network.point_at(position) {
+            Some(index) => index,
+            None => network.add_point(Point {
+                x: position.0,
+                y: position.1,
+                is_intersection: false,
+            }),
+        };
+        if nodes[&id].is_signal && !signals.contains(&index) {
+            signals.push(index);
+        }
+        points.insert(id, index);
+    }
+
+    for way in &ways {
+        let speed_limit = match way.maxspeed {
+            // km/h -> units per second
+            Some(kmh) => kmh / 3.6 / meters_per_unit,
+            None => road_config.speed_limit,
+        };
+        let lanes = match way.lanes {
+            // The lanes tag counts both directions of a two-way road
+            Some(total) if way.oneway == 0 => (total / 2).max(1),
+            Some(total) => total.max(1),
+            None => road_config.lanes,
+        };
+
+        // Split the way into edges between consecutive junctions,
+        // measuring the length along the shape points in between
+        let mut start = way.nodes[0];
+        let mut length = 0.0;
+        for pair in way.nodes.windows(2) {
+            let (ax, ay) = project(&nodes[&pair[0]]);
+            let (bx, by) = project(&nodes[&pair[1]]);
+            length += ((bx - ax).powi(2) + (by - ay).powi(2)).sqrt();
+
+            if let Some(&end) = points.get(&pair[1]) {
+                let from = points[&start];
+                if from != end {
+                    let (a, b) = if way.oneway < 0 { (end, from) } else { (from, end) };
+                    add_edge(&mut network, a, b, lanes, speed_limit, length as f32);
+                    if way.oneway == 0 {
+                        add_edge(&mut network, b, a, lanes, speed_limit, length as f32);
+                    }
+                }
+                start = pair[1];
+                length = 0.0;
+            }
+        }
+    }
+
+    network.update_intersections();
+    Ok((network, signals))
+}
+
+// Add an edge unless the same one already exists, keeping the way's real
+// length when it is longer than the straight line between the junctions
+fn add_edge(network: &mut RoadNetwork, from: usize, to: usize, lanes: u8, speed_limit: f32, length: f32) {
+    if network.edge_between(from, to).is_some() {
+        return;
+    }
+    let edge = network.add_edge(from, to, lanes, speed_limit);
+    network.edges[edge].length = network.edges[edge].length.max(length);
+}
+
+fn attribute<T: std::str::FromStr>(element: &roxmltree::Node, name: &str) -> Result<T, NetworkError> {
+    element.attribute(name)
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| NetworkError::Parse(format!(
+            "<{}> at byte {} has a missing or invalid `{}` attribute",
+            element.tag_name().name(),
+            element.range().start,
+            name
+        )))
+}
+
+// Value of a <tag k=".." v=".."/> child
+fn tag<'a>(element: &roxmltree::Node<'a, '_>, key: &str) -> Option<&'a str> {
+    element.children()
+        .filter(|n| n.has_tag_name("tag"))
+        .find(|n| n.attribute("k") == Some(key))
+        .and_then(|n| n.attribute("v"))
+}
+
+fn oneway(way: &roxmltree::Node, highway: &str) -> i8 {
+    match tag(way, "oneway") {
+        Some("yes") | Some("true") | Some("1") => 1,
+        Some("-1") | Some("reverse") => -1,
+        Some(_) => 0,
+        // Motorways and roundabouts are one-way unless tagged otherwise
+        None if highway == "motorway" || tag(way, "junction") == Some("roundabout") => 1,
+        None => 0,
+    }
+}
+
+// "50" and "50 km/h" are km/h, "30 mph" is converted.
+// Values like "none" or "signals" give None.
+fn parse_maxspeed(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let (number, factor) = match value.strip_suffix("mph") {
+        Some(number) => (number, 1.609_344),
+        None => (value.trim_end_matches("km/h"), 1.0),
+    };
+    number.trim().parse::<f32>().ok()
+        .filter(|speed| *speed > 0.0)
+        .map(|speed| speed * factor)
+}
@@ -1,6 +1,8 @@
 //point.rs
+use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter, Result};
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Point {
     pub x: i32,
     pub y: i32,
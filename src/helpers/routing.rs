@@ -0,0 +1,127 @@
+// routing.rs
+use super::network::RoadNetwork;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+// Entry of the A* open set, ordered so the BinaryHeap pops the lowest estimate
+struct State {
+    // Time so far plus the heuristic
+    estimate: f32,
+    time: f32,
+    point: usize,
+}
+
+impl PartialEq for State {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for State {}
+
+impl PartialOrd for State {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for State {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed for a min-heap, ties broken by point index so the
+        // result doesn't depend on insertion order
+        other.estimate.total_cmp(&self.estimate)
+            .then_with(|| other.point.cmp(&self.point))
+    }
+}
+
+// Time to drive an edge at its speed limit
+pub fn free_flow_time(network: &RoadNetwork, edge: usize) -> f32 {
+    network.edges[edge].length / network.edges[edge].speed_limit
+}
+
+// Fastest route at the speed limits avoiding closed roads,
+// as a list of edge indexes
+pub fn plan_route(network: &RoadNetwork, from: usize, to: usize) -> Option<Vec<usize>> {
+    shortest_path(network, from, to, |edge| {
+        if network.edges[edge].closed {
+            f32::INFINITY
+        } else {
+            free_flow_time(network, edge)
+        }
+    })
+}
+
+// Sum of the given travel times along a route
+pub fn route_time(route: &[usize], travel_times: &[f32]) -> f32 {
+    route.iter().map(|&edge| travel_times[edge]).sum()
+}
+
+// A* search between two points, returning the edges to follow.
+// `cost` gives the travel time of an edge (f32::INFINITY for closed ones) and
+// must never be below free_flow_time, otherwise the heuristic overestimates.
+pub fn shortest_path<F: Fn(usize) -> f32>(
+    network: &RoadNetwork,
+    from: usize,
+    to: usize,
+    cost: F,
+) -> Option<Vec<usize>> {
+    if from == to {
+        return Some(Vec::new());
+    }
+    if network.edges.is_empty() {
+        return None;
+    }
+
+    // Straight line distance at the highest speed limit never overestimates
+    let max_speed = network.edges.iter().map(|e| e.speed_limit).fold(0.0, f32::max);
+    let target = network.position(to);
+    let heuristic = |point: usize| -> f32 {
+        let (x, y) = network.position(point);
+        let dx = (target.0 - x) as f32;
+        let dy = (target.1 - y) as f32;
+        (dx * dx + dy * dy).sqrt() / max_speed
+    };
+
+    let mut best = vec![f32::INFINITY; network.points.len()];
+    // Edge used to reach every point on the best route found so far
+    let mut came_by: Vec<Option<usize>> = vec![None; network.points.len()];
+    let mut open = BinaryHeap::new();
+
+    best[from] = 0.0;
+    open.push(State { estimate: heuristic(from), time: 0.0, point: from });
+
+    while let Some(State { time, point, .. }) = open.pop() {
+        if point == to {
+            break;
+        }
+        // Skip stale entries of points already reached faster
+        if time > best[point] {
+            continue;
+        }
+
+        for &edge in network.outgoing_edges(point) {
+            let edge_cost = cost(edge);
+            if !edge_cost.is_finite() {
+                continue;
+            }
+            let next = network.edges[edge].to;
+            let next_time = time + edge_cost;
+            if next_time < best[next] {
+                best[next] = next_time;
+                came_by[next] = Some(edge);
+                open.push(State { estimate: next_time + heuristic(next), time: next_time, point: next });
+            }
+        }
+    }
+
+    // Walk back from the destination
+    came_by[to]?;
+    let mut route = Vec::new();
+    let mut point = to;
+    while let Some(edge) = came_by[point] {
+        route.push(edge);
+        point = network.edges[edge].from;
+    }
+    route.reverse();
+    Some(route)
+}
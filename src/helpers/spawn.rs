@@ -0,0 +1,182 @@
+// spawn.rs
+use super::network::RoadNetwork;
+use super::vehicle::VehicleType;
+use rand::Rng;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+
+// Vehicles per tick of the default policy
+const DEFAULT_COUNT: u32 = 3;
+
+// How many vehicles enter the simulation, and the most that may drive at once
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SpawnConfig {
+    #[serde(flatten)]
+    pub policy: SpawnPolicy,
+    // No new vehicles while this many are on the roads or waiting to
+    // enter an open network
+    pub max_vehicles: Option<usize>,
+}
+
+// A table without a policy keeps the default one, so `max_vehicles` (or the
+// count) can be set on its own
+impl<'de> Deserialize<'de> for SpawnConfig {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut table = serde_json::Map::deserialize(deserializer)?;
+        let max_vehicles = match table.remove("max_vehicles") {
+            Some(value) => serde_json::from_value(value).map_err(D::Error::custom)?,
+            None => None,
+        };
+        if !table.contains_key("policy") {
+            table.insert("policy".to_string(), "per_tick".into());
+            table.entry("count").or_insert(DEFAULT_COUNT.into());
+        }
+        let policy = serde_json::from_value(table.into()).map_err(D::Error::custom)?;
+        Ok(SpawnConfig { policy, max_vehicles })
+    }
+}
+
+// Rates are vehicles per simulated second
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "policy", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SpawnPolicy {
+    // The same number of vehicles every tick
+    PerTick { count: u32 },
+    // Evenly spread over time, e.g. 0.5 is one vehicle every two seconds
+    FixedRate { rate: f64 },
+    // Random arrivals at every entry point, each at the given mean rate.
+    // Without entry points every point of the network is one, or every
+    // source of an open network.
+    Poisson {
+        rate: f64,
+        #[serde(default)]
+        entry_points: Vec<(i32, i32)>,
+    },
+    // Random arrivals over the whole network at a rate that changes over
+    // time: [time, rate] pairs with straight lines in between, repeated
+    // every `period` seconds if given (e.g. 86400 for a daily profile)
+    Curve {
+        points: Vec<(f64, f64)>,
+        #[serde(default)]
+        period: Option<f64>,
+    },
+    // Trips between zones or points from a CSV or JSON origin-destination
+    // matrix (see demand.rs), with zones as lists of points
+    OdMatrix {
+        file: String,
+        #[serde(default)]
+        zones: BTreeMap<String, Vec<(i32, i32)>>,
+    },
+}
+
+// A vehicle about to enter, the parts left out are picked at random
+#[derive(Clone, Debug, Default)]
+pub struct Arrival {
+    pub start: Option<usize>,
+    pub destination: Option<usize>,
+    pub vehicle_type: Option<VehicleType>,
+}
+
+impl Default for SpawnPolicy {
+    fn default() -> Self {
+        SpawnPolicy::PerTick { count: DEFAULT_COUNT }
+    }
+}
+
+impl SpawnPolicy {
+    // Vehicles arriving in the tick that ends at `time`. Per tick and fixed
+    // rate spawns don't use the generator, so their runs stay the same
+    // whatever else draws from it. The OD matrix is loaded when the
+    // simulation is built, its arrivals come from Demand. `sources` are the
+    // entry points of an open network.
+    pub fn arrivals(
+        &self,
+        time: f64,
+        dt: f64,
+        network: &RoadNetwork,
+        sources: Option<&[usize]>,
+        rng: &mut impl Rng,
+    ) -> Vec<Arrival> {
+        match self {
+            SpawnPolicy::PerTick { count } => vec![Arrival::default(); *count as usize],
+            SpawnPolicy::FixedRate { rate } => {
+                // Whole vehicles due by now minus those due a tick ago
+                let count = (rate * time).floor() - (rate * (time - dt)).floor();
+                vec![Arrival::default(); count.max(0.0) as usize]
+            },
+            SpawnPolicy::Poisson { rate, entry_points } => {
+                let entries: Vec<usize> = if !entry_points.is_empty() {
+                    entry_points.iter().filter_map(|&position| network.point_at(position)).collect()
+                } else if let Some(sources) = sources {
+                    sources.to_vec()
+                } else {
+                    (0..network.points.len()).collect()
+                };
+                let mut arrivals = Vec::new();
+                for entry in entries {
+                    let count = poisson(rate * dt, rng);
+                    let arrival = Arrival {
+                        start: Some(entry),
+                        ..Arrival::default()
+                    };
+                    arrivals.extend(std::iter::repeat_n(arrival, count as usize));
+                }
+                arrivals
+            },
+            SpawnPolicy::Curve { points, period } => {
+                let count = poisson(curve_rate(points, *period, time) * dt, rng);
+                vec![Arrival::default(); count as usize]
+            },
+            SpawnPolicy::OdMatrix { .. } => Vec::new(),
+        }
+    }
+}
+
+// Rate of a curve at a time, flat before the first and after the last point
+pub fn curve_rate(points: &[(f64, f64)], period: Option<f64>, time: f64) -> f64 {
+    let time = match period {
+        Some(period) => time.rem_euclid(period),
+        None => time,
+    };
+    let Some(&(first_time, first_rate)) = points.first() else {
+        return 0.0;
+    };
+    if time <= first_time {
+        return first_rate;
+    }
+    for pair in points.windows(2) {
+        let ((t0, r0), (t1, r1)) = (pair[0], pair[1]);
+        if time <= t1 {
+            if t1 <= t0 {
+                return r1;
+            }
+            return r0 + (r1 - r0) * (time - t0) / (t1 - t0);
+        }
+    }
+    points[points.len() - 1].1
+}
+
+// Number of arrivals with the given mean, by multiplying uniform numbers
+// until the product drops below e^-mean (Knuth). Large means are split in
+// parts, which keeps e^-mean from underflowing.
+pub fn poisson(mean: f64, rng: &mut impl Rng) -> u32 {
+    const PART: f64 = 10.0;
+
+    if mean.is_nan() || mean <= 0.0 {
+        return 0;
+    }
+    let mut count = 0;
+    let mut left = mean;
+    while left > 0.0 {
+        let part = left.min(PART);
+        left -= part;
+        let limit = (-part).exp();
+        let mut product: f64 = rng.random();
+        while product > limit {
+            count += 1;
+            product *= rng.random::<f64>();
+        }
+    }
+    count
+}
@@ -0,0 +1,101 @@
+// traffic.rs
+use super::light::TrafficLight;
+use super::network::RoadNetwork;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+// Distance kept to the cell of the vehicle ahead, a bit over half a cell
+// so the follower's progress still rounds to the cell behind
+const CELL_GAP: f32 = 0.51;
+
+// A unit long piece of one lane: (edge, lane, cell along the edge).
+// Cell k holds the progress that rounds to k, like the drawn positions.
+pub type Cell = (usize, u8, i32);
+
+pub fn cell_index(progress: f32) -> i32 {
+    progress.round() as i32
+}
+
+// A vehicle holding a cell of a lane
+#[derive(Clone, Debug)]
+pub struct Occupant {
+    pub cell: i32,
+    pub vehicle_id: u64,
+    pub speed: f32,
+}
+
+// Snapshot of the road conditions at the start of a tick,
+// shared by all vehicle update tasks
+pub struct TrafficView {
+    pub network: Arc<RoadNetwork>,
+    // Live travel time of every edge (infinite when closed)
+    pub travel_times: Vec<f32>,
+    // Traffic light of every point, if it has one
+    pub lights: Vec<Option<TrafficLight>>,
+    // Vehicles of every (edge, lane), sorted along the edge
+    pub occupancy: HashMap<(usize, u8), Vec<Occupant>>,
+}
+
+impl TrafficView {
+    pub fn light_at(&self, point: usize) -> Option<&TrafficLight> {
+        self.lights.get(point).and_then(|light| light.as_ref())
+    }
+
+    // Next vehicle ahead on the same lane
+    pub fn leader(&self, edge: usize, lane: u8, progress: f32, vehicle_id: u64) -> Option<&Occupant> {
+        let cell = cell_index(progress);
+        self.occupancy.get(&(edge, lane))
+            .and_then(|occupants| {
+                occupants.iter().find(|other| other.cell > cell && other.vehicle_id != vehicle_id)
+            })
+    }
+
+    // Furthest progress on a lane that stays out of the cell of the next
+    // vehicle ahead, infinite when the lane is clear
+    pub fn free_until(&self, edge: usize, lane: u8, progress: f32, vehicle_id: u64) -> f32 {
+        match self.leader(edge, lane, progress, vehicle_id) {
+            Some(leader) => leader.cell as f32 - CELL_GAP,
+            None => f32::INFINITY,
+        }
+    }
+
+    // Lane to enter an edge on: the one with the most room ahead, lowest
+    // first on a tie. None while the first cell of every lane is held.
+    pub fn entry_lane(&self, edge: usize, vehicle_id: u64) -> Option<u8> {
+        (0..self.network.edges[edge].lanes.max(1))
+            .filter(|&lane| !self.is_held((edge, lane, 0), vehicle_id))
+            .min_by(|&a, &b| {
+                let room_a = self.free_until(edge, a, 0.0, vehicle_id);
+                let room_b = self.free_until(edge, b, 0.0, vehicle_id);
+                room_b.total_cmp(&room_a)
+            })
+    }
+
+    // Whether another vehicle holds a cell
+    pub fn is_held(&self, cell: Cell, vehicle_id: u64) -> bool {
+        let (edge, lane, index) = cell;
+        self.occupancy.get(&(edge, lane))
+            .is_some_and(|occupants| {
+                occupants.iter().any(|other| other.cell == index && other.vehicle_id != vehicle_id)
+            })
+    }
+}
+
+// Two vehicles found in the same cell
+#[derive(Debug)]
+pub struct OccupancyError {
+    pub cell: Cell,
+    pub vehicles: (u64, u64),
+}
+
+impl Display for OccupancyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (edge, lane, index) = self.cell;
+        write!(
+            f,
+            "vehicles {} and {} both occupy cell {} of lane {} on edge {}",
+            self.vehicles.0, self.vehicles.1, index, lane, edge
+        )
+    }
+}
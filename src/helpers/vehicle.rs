@@ -1,181 +1,624 @@
 // vehicle.rs
-use std::sync::atomic::Ordering;
+use std::sync::Arc;
 use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
 
-// use crate::variables::{CAR_ID_COUNTER, GRID_HEIGHT, GRID_WIDTH};
+use super::config::VehicleProfiles;
+use super::light::LightState;
+use super::network::RoadNetwork;
+use super::routing;
+use super::spawn::Arrival;
+use super::traffic::{self, Cell, TrafficView};
 
-use super::variables::{GRID_HEIGHT, GRID_WIDTH, CAR_ID_COUNTER};
+// Vehicles wait this far before a signalized point
+const STOP_LINE_OFFSET: f32 = 1.0;
 
-#[derive(Clone, Debug)]
+// Slower than this counts as standing still, in units per second
+const STOPPED_SPEED: f32 = 0.1;
+
+// Car-following model (Intelligent Driver Model) settings shared by all types.
+// Gap kept to a stopped vehicle or stop line, in units
+const MIN_GAP: f32 = 0.5;
+// Time gap drivers keep to the vehicle ahead, in seconds
+const TIME_HEADWAY: f32 = 1.0;
+// How sharply drivers stop accelerating close to their desired speed
+const ACCELERATION_EXPONENT: i32 = 4;
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum VehicleType {
     Car,
     Bus,
     Emergency,
 }
 
-#[derive(Clone)]
+impl VehicleType {
+    // Visual identifier used when printing the grid
+    pub fn symbol(&self) -> char {
+        match self {
+            VehicleType::Car => 'C',
+            VehicleType::Bus => 'B',
+            VehicleType::Emergency => 'E',
+        }
+    }
+}
+
+// When a vehicle looks for a faster route during its trip
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "policy", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ReroutePolicy {
+    // Keep the route planned at spawn (closed roads are still avoided)
+    Never,
+    // Re-plan every few ticks
+    Periodic { every_ticks: u32 },
+    // Re-plan when an edge ahead takes `ratio` times its free-flow time
+    OnThreshold { ratio: f32 },
+}
+
+impl Default for ReroutePolicy {
+    fn default() -> Self {
+        ReroutePolicy::OnThreshold { ratio: 1.5 }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum RerouteReason {
+    // A road on the route was closed
+    Closed,
+    // A road on the route got slower than the policy's threshold
+    Congested,
+    // Periodic check of the policy
+    Periodic,
+}
+
+// A vehicle switched to a faster route
+#[derive(Clone, Debug)]
+pub struct Reroute {
+    pub vehicle_id: u64,
+    pub reason: RerouteReason,
+    // Expected remaining travel time on the old and the new route
+    pub old_travel_time: f32,
+    pub new_travel_time: f32,
+}
+
+// What happened to a vehicle during one update
+#[derive(Default)]
+pub struct UpdateReport {
+    // Edges left during the update with their delay factor: time spent
+    // divided by the vehicle's own free-flow time (1.0 = no delay)
+    pub edge_delays: Vec<(usize, f32)>,
+    pub reroute: Option<Reroute>,
+}
+
+// What a vehicle did since it entered the grid, reported when it arrives
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Trip {
+    // Tick it entered the grid, set by the simulation
+    pub spawn_tick: Option<u64>,
+    // Units driven
+    pub distance: f32,
+    // Seconds spent slower than STOPPED_SPEED
+    pub stopped_time: f32,
+    // Seconds the distance driven takes at the vehicle's top speed
+    // within the speed limits
+    pub free_flow_time: f32,
+    // Gave up on the way, no route to the destination
+    pub abandoned: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Vehicle {
     pub id: u64,
     // Use visual identifier (e.g., 'C', 'B', 'E')
     pub vehicle_type: VehicleType,
-    pub current_position: (i32, i32),
+    // Exact position between the points of the network
+    pub current_position: (f32, f32),
     // Units per second
-    pub current_speed: i32,
-    pub max_speed: i32,
+    pub current_speed: f32,
+    pub max_speed: f32,
+    // Units per second squared, from the profile of the vehicle type
+    pub acceleration: f32,
+    pub braking: f32,
     pub destination: (i32, i32),
     pub priority: u8,
+    // Planned path as edge indexes of the road network
+    pub route: Vec<usize>,
+    // Index in route of the edge the vehicle is on
+    pub route_index: usize,
+    // Lane of that edge, 0 is the first one
+    pub lane: u8,
+    // Distance already driven along that edge
+    pub edge_progress: f32,
+    // Time spent on the current edge so far
+    pub time_on_edge: f32,
+    pub reroute_policy: ReroutePolicy,
+    pub ticks_since_reroute: u32,
+    // Decided to cross on yellow, keeps going if the light turns red
+    pub running_yellow: bool,
+    #[serde(default)]
+    pub trip: Trip,
 }
 
 impl Vehicle {
     pub fn new(
         id: u64,
         vehicle_type: VehicleType,
-        current_position: (i32, i32),
+        current_position: (f32, f32),
         // Units per second
-        current_speed: i32,
-        max_speed: i32,
+        current_speed: f32,
+        max_speed: f32,
         destination: (i32, i32),
         priority: u8,
     ) -> Vehicle {
-        return Vehicle {
+        Vehicle {
             id,
             vehicle_type,
             current_speed,
             max_speed,
+            acceleration: 1.0,
+            braking: 1.5,
             current_position,
             destination,
             priority,
-        };
+            // Planned once the vehicle knows the road network
+            route: Vec::new(),
+            route_index: 0,
+            lane: 0,
+            edge_progress: 0.0,
+            time_on_edge: 0.0,
+            reroute_policy: ReroutePolicy::default(),
+            ticks_since_reroute: 0,
+            running_yellow: false,
+            trip: Trip::default(),
+        }
     }
 
-    pub async fn generate_vehicle() -> Self {
+    // Spawn a vehicle, picking at random what the arrival leaves open.
+    // The caller hands out the id and a generator of its own to every
+    // vehicle, so spawning several at once gives the same result whichever
+    // task runs first. None when the vehicle would have nowhere to go.
+    pub async fn generate_vehicle(
+        id: u64,
+        network: Arc<RoadNetwork>,
+        profiles: VehicleProfiles,
+        arrival: Arrival,
+        mut rng: ChaCha8Rng,
+    ) -> Option<Self> {
         tokio::task::spawn_blocking(move || {
-            // randomly generate the car type
-            let mut rng = rand::rng();
-            // The range 1..=3 generates numbers from 1 to 3 (inclusive)
-            let num = rng.random_range(1..=3);
-
-            // Match the random number to a vehicle type
-            let vehicle_type = match num {
-                1 => VehicleType::Car,
-                2 => VehicleType::Bus,
-                3 => VehicleType::Emergency,
-                // since num is always between 1 and 3
-                _ => unreachable!(), 
-            };
+            let vehicle_type = arrival.vehicle_type.unwrap_or_else(|| {
+                // randomly generate the car type
+                // The range 1..=3 generates numbers from 1 to 3 (inclusive)
+                let num = rng.random_range(1..=3);
 
-            // Generate poisition
-            let x_position = rng.random_range(0..=(GRID_WIDTH * 10));
-            let y_position =
-            // If x is not 0 and not divisible by 10, then generate y as a multiple of 10.
-            if x_position != 0 || x_position % 10 != 0 {
-                rng.random_range(0..=GRID_HEIGHT) * 10 
-            } else {
-                rng.random_range(0..=GRID_HEIGHT * 10)
-            };
+                // Match the random number to a vehicle type
+                match num {
+                    1 => VehicleType::Car,
+                    2 => VehicleType::Bus,
+                    3 => VehicleType::Emergency,
+                    // since num is always between 1 and 3
+                    _ => unreachable!(),
+                }
+            });
 
-            // Generate speed based on the car type
-            let speed = match num {
-                1 => 2,
-                2 => 1,
-                3 => 3,
-                _ => unreachable!(),
-            };
+            // Generate poisition on one of the network's points,
+            // so vehicles also start on the road for loaded networks
+            let start = arrival.start.unwrap_or_else(|| rng.random_range(0..network.points.len()));
+            let (x, y) = network.position(start);
 
-            // Generate prioirty based on the car type (higher is better)
-            let priority = match num {
-                1 => 1,
-                2 => 2,
-                3 => 3,
-                _ => unreachable!(),
+            let (finish, route) = match arrival.destination {
+                // Already there, no trip to make
+                Some(destination) if destination == start => return None,
+                // Without a route the vehicle gives up on its first update
+                Some(destination) => {
+                    (destination, routing::plan_route(&network, start, destination).unwrap_or_default())
+                },
+                // Generate destination on another point of the network that
+                // can be reached from the start (one-way roads may prevent it)
+                None => (0..10).find_map(|_| {
+                    let candidate = rng.random_range(0..network.points.len());
+                    routing::plan_route(&network, start, candidate)
+                        .filter(|route| !route.is_empty())
+                        .map(|route| (candidate, route))
+                })?,
             };
 
-            // Generate destination (both should be divisible by 10 or 0)
-            let x_final = rng.random_range(0..=GRID_WIDTH) * 10;
-            let y_final = rng.random_range(0..=GRID_HEIGHT) * 10;
-
             // Create a vehicle
-            Vehicle::new(
-                CAR_ID_COUNTER.fetch_add(1, Ordering::Relaxed), 
-                vehicle_type, 
-                (x_position, y_position), 
-                speed, 
-                // When car just generated current speed is same as max speed.
-                speed, 
-                (x_final,y_final), 
-                priority,
-            )
+            let mut vehicle = Vehicle::with_profile(id, vehicle_type, (x, y), network.position(finish), &profiles);
+            vehicle.route = route;
+            Some(vehicle)
         }).await.expect("Vehicle generation task failed")
     }
 
-    pub async fn update(&mut self) {
+    // Vehicle standing at a point, with the configured profile of its type.
+    // The route is planned on the first update unless set before.
+    pub fn with_profile(
+        id: u64,
+        vehicle_type: VehicleType,
+        position: (i32, i32),
+        destination: (i32, i32),
+        profiles: &VehicleProfiles,
+    ) -> Vehicle {
+        // Speed and priority (higher is better) come from the
+        // configured profile of the car type
+        let profile = profiles.profile(&vehicle_type).clone();
+        let mut vehicle = Vehicle::new(
+            id,
+            vehicle_type,
+            (position.0 as f32, position.1 as f32),
+            // Vehicles start from standstill and speed up
+            0.0,
+            profile.speed,
+            destination,
+            profile.priority,
+        );
+        vehicle.reroute_policy = profile.reroute;
+        vehicle.acceleration = profile.acceleration;
+        vehicle.braking = profile.braking;
+        vehicle
+    }
+
+    // Move the vehicle along its route for a tick of `dt` seconds
+    pub async fn update(&mut self, view: &TrafficView, dt: f32) -> UpdateReport {
+        let mut report = UpdateReport::default();
+        let network = &view.network;
+
         // Car at destination
-        if self.current_position == self.destination {
-            return 
+        if self.has_arrived() {
+            return report;
+        }
+
+        // Vehicles created without a route plan one on their first update
+        if self.route.is_empty() {
+            if !self.plan_route(network) {
+                // No way to get there (or not on a road at all), give up
+                // the trip where the vehicle stands
+                self.destination = self.rounded_position();
+                self.current_position = (self.destination.0 as f32, self.destination.1 as f32);
+                self.trip.abandoned = true;
+                return report;
+            }
+            // Already standing at the destination point
+            if self.route.is_empty() {
+                self.current_position = self.position_on_route(network);
+                return report;
+            }
         }
 
-        // Car needs to move on y only
-        if self.current_position.0 == self.destination.0{
-            let distance = self.destination.1 - self.current_position.1;
-            if self.current_speed <= distance {
-                self.current_position.1 += self.current_speed;
-            } else {
-                // To make sure that car doesn't go beyond destination
-                self.current_position.1 = self.destination.1;
+        report.reroute = self.check_reroute(view);
+        self.time_on_edge += dt;
+
+        // The car-following model picks the speed for this tick, aiming for
+        // the top speed the current road allows
+        let current_edge = &network.edges[self.route[self.route_index]];
+        let desired_speed = self.max_speed.min(current_edge.speed_limit);
+        let obstacle = self.obstacle_ahead(view);
+        let acceleration = self.idm_acceleration(desired_speed, obstacle);
+        let speed = (self.current_speed + acceleration * dt)
+            .min(desired_speed.max(self.current_speed))
+            .max(0.0);
+
+        // Distance covered while changing speed over the tick
+        let mut distance = (self.current_speed + speed) / 2.0 * dt;
+        let planned = distance;
+        let mut travelled = 0.0;
+        // Time the same distance takes at the top speed, for the trip
+        let mut free_flow_time = 0.0;
+
+        // Follow the route, turning onto the next edge at each point
+        while self.route_index < self.route.len() {
+            let edge = self.route[self.route_index];
+            let length = network.edges[edge].length;
+            let top_speed = self.max_speed.min(network.edges[edge].speed_limit);
+
+            // Queue behind the vehicle ahead in the same lane
+            let mut limit = length.min(view.free_until(edge, self.lane, self.edge_progress, self.id));
+
+            // Brake to hold at the stop line of a red light
+            let stop_line = (length - STOP_LINE_OFFSET).max(0.0);
+            if self.edge_progress <= stop_line && self.must_stop_for_light(view, edge, stop_line) {
+                limit = limit.min(stop_line);
+            }
+
+            if self.edge_progress + distance < length || limit < length {
+                let step = distance.min(limit - self.edge_progress).max(0.0);
+                self.edge_progress += step;
+                travelled += step;
+                free_flow_time += step / top_speed;
+                break;
             }
-        } 
-
-        // Car needs to move on x only
-        else if self.current_position.1 == self.destination.1 {
-            let distance = self.destination.0 - self.current_position.0;
-            if self.current_speed <= distance {
-                self.current_position.0 += self.current_speed;
-            } else {
-                self.current_position.0 = self.destination.0;
+            // Wait at the end of the edge while the next road is closed
+            // or the first cell of each of its lanes is taken
+            let mut lane = 0;
+            if let Some(&next) = self.route.get(self.route_index + 1) {
+                let entry = if network.edges[next].closed {
+                    None
+                } else {
+                    view.entry_lane(next, self.id)
+                };
+                match entry {
+                    Some(entry) => lane = entry,
+                    None => {
+                        travelled += length - self.edge_progress;
+                        free_flow_time += (length - self.edge_progress) / top_speed;
+                        self.edge_progress = length;
+                        break;
+                    }
+                }
             }
-        } 
-        
-        // Car needs to move on x and y
-        else if self.current_position.1 == 0 || self.current_position.1 % 10 == 0 {
-            if self.current_position.0 < self.destination.0 {
-                // Car moves forward on x untill it same as destination
-                let distance_x = self.destination.0 - self.current_position.0;
-
-                if self.current_speed <= distance_x {
-                    self.current_position.0 += self.current_speed;
+            distance -= length - self.edge_progress;
+            travelled += length - self.edge_progress;
+            free_flow_time += (length - self.edge_progress) / top_speed;
+            report.edge_delays.push((edge, self.time_on_edge / self.free_flow_time(network, edge)));
+            self.route_index += 1;
+            self.lane = lane;
+            self.edge_progress = 0.0;
+            self.time_on_edge = 0.0;
+            self.running_yellow = false;
+        }
+
+        // A vehicle or closed road the model didn't see coming
+        // cut the move short
+        self.current_speed = if travelled < planned - 1e-3 { travelled / dt } else { speed };
+        self.current_position = self.position_on_route(network);
+
+        self.trip.distance += travelled;
+        self.trip.free_flow_time += free_flow_time;
+        if self.current_speed < STOPPED_SPEED {
+            self.trip.stopped_time += dt;
+        }
+        report
+    }
+
+    // Whether the light at the end of an edge tells this vehicle to stop.
+    // On yellow it only stops if it can do so comfortably before the stop line.
+    fn must_stop_for_light(&mut self, view: &TrafficView, edge: usize, stop_line: f32) -> bool {
+        let edge = &view.network.edges[edge];
+        let light = match view.light_at(edge.to) {
+            Some(light) => light,
+            None => return false,
+        };
+
+        match light.state_for(edge.direction) {
+            LightState::Green => false,
+            LightState::Red => !self.running_yellow,
+            LightState::Yellow => {
+                if self.running_yellow {
+                    return false;
+                }
+                let speed = self.current_speed;
+                let braking_distance = speed * speed / (2.0 * self.braking);
+                if braking_distance <= stop_line - self.edge_progress {
+                    true
                 } else {
-                    self.current_position.0 = self.destination.0;
+                    // Too close to stop, clear the intersection
+                    self.running_yellow = true;
+                    false
                 }
-            } else {
-                // Car moves back on x untill it same as destination
-                let distance_x = self.destination.0 - self.current_position.0;
+            },
+        }
+    }
+
+    // Closest thing ahead the vehicle has to stop for: the vehicle in front,
+    // a red light or a road it can't turn onto. Gives the distance to where
+    // it would have to stand still and how fast that point is moving.
+    fn obstacle_ahead(&mut self, view: &TrafficView) -> Option<(f32, f32)> {
+        let network = &view.network;
+        let edge = self.route[self.route_index];
+        let length = network.edges[edge].length;
+        let mut obstacles = Vec::new();
+
+        if let Some(leader) = view.leader(edge, self.lane, self.edge_progress, self.id) {
+            let stop = view.free_until(edge, self.lane, self.edge_progress, self.id);
+            obstacles.push((stop - self.edge_progress, leader.speed));
+        }
+        let stop_line = (length - STOP_LINE_OFFSET).max(0.0);
+        if self.edge_progress <= stop_line && self.must_stop_for_light(view, edge, stop_line) {
+            obstacles.push((stop_line - self.edge_progress, 0.0));
+        }
 
-                if self.current_speed <= distance_x {
-                    self.current_position.0 -= self.current_speed;
+        // Look onto the next edge of the route when this one is clear
+        if obstacles.is_empty() {
+            if let Some(&next) = self.route.get(self.route_index + 1) {
+                let to_end = length - self.edge_progress;
+                let entry = if network.edges[next].closed {
+                    None
                 } else {
-                    self.current_position.0 = self.destination.0;
+                    view.entry_lane(next, self.id)
+                };
+                match entry {
+                    Some(lane) => {
+                        if let Some(leader) = view.leader(next, lane, 0.0, self.id) {
+                            let stop = view.free_until(next, lane, 0.0, self.id);
+                            obstacles.push((to_end + stop, leader.speed));
+                        }
+                    },
+                    None => obstacles.push((to_end, 0.0)),
                 }
             }
         }
-        else if self.current_position.1 < self.destination.1 {
-            // Car moves forward on y untill it same as destination
-            let distance_y = self.destination.1 - self.current_position.1;
-
-            if self.current_speed <= distance_y {
-                self.current_position.1 += self.current_speed;
-            } else {
-                self.current_position.1 = self.destination.1;
-            }
+
+        obstacles.into_iter().min_by(|a, b| a.0.total_cmp(&b.0))
+    }
+
+    // Intelligent Driver Model: accelerate towards the desired speed, brake
+    // to keep a safe gap to the obstacle ahead
+    fn idm_acceleration(&self, desired_speed: f32, obstacle: Option<(f32, f32)>) -> f32 {
+        let speed = self.current_speed;
+        let free_road = 1.0 - (speed / desired_speed).powi(ACCELERATION_EXPONENT);
+
+        let interaction = match obstacle {
+            Some((distance, obstacle_speed)) => {
+                let gap = (distance + MIN_GAP).max(0.01);
+                let closing = speed * (speed - obstacle_speed) / (2.0 * (self.acceleration * self.braking).sqrt());
+                let wanted_gap = MIN_GAP + (speed * TIME_HEADWAY + closing).max(0.0);
+                (wanted_gap / gap).powi(2)
+            },
+            None => 0.0,
+        };
+        self.acceleration * (free_road - interaction)
+    }
+
+    // Apply the reroute policy, switching to a faster route if there is one
+    fn check_reroute(&mut self, view: &TrafficView) -> Option<Reroute> {
+        let network = &view.network;
+        let travel_times = &view.travel_times;
+        self.ticks_since_reroute += 1;
+
+        // The vehicle finishes the edge it is on, unless it hasn't started it yet
+        let current = self.route[self.route_index];
+        let (kept, from, ahead) = if self.edge_progress == 0.0 {
+            (None, network.edges[current].from, &self.route[self.route_index..])
         } else {
-            // Car moves back on y untill it same as destination
-            let distance_1 = self.destination.1 - self.current_position.1;
+            (Some(current), network.edges[current].to, &self.route[self.route_index + 1..])
+        };
 
-            if self.current_speed <= distance_1 {
-                self.current_position.1 -= self.current_speed;
-            } else {
-                self.current_position.1 = self.destination.1;
+        // Closed roads ahead can't be driven whatever the policy
+        let reason = if ahead.iter().any(|&edge| network.edges[edge].closed) {
+            RerouteReason::Closed
+        } else {
+            match self.reroute_policy {
+                ReroutePolicy::Never => return None,
+                ReroutePolicy::Periodic { every_ticks } => {
+                    if self.ticks_since_reroute < every_ticks {
+                        return None;
+                    }
+                    RerouteReason::Periodic
+                },
+                ReroutePolicy::OnThreshold { ratio } => {
+                    let congested = ahead.iter().any(|&edge| {
+                        travel_times[edge] >= ratio * routing::free_flow_time(network, edge)
+                    });
+                    if !congested {
+                        return None;
+                    }
+                    RerouteReason::Congested
+                },
             }
+        };
+        self.ticks_since_reroute = 0;
+
+        let finish = network.point_at(self.destination)?;
+        let old_travel_time = routing::route_time(ahead, travel_times);
+        let new_route = routing::shortest_path(network, from, finish, |edge| travel_times[edge])?;
+        let new_travel_time = routing::route_time(&new_route, travel_times);
+        // Only switch to a strictly faster route
+        if new_travel_time >= old_travel_time {
+            return None;
         }
+        // Not started on its edge yet, the vehicle may swap it for another
+        // with other lanes. Stay on the old route if none of them is free.
+        if kept.is_none() && new_route.first() != Some(&current) {
+            self.lane = view.entry_lane(*new_route.first()?, self.id)?;
+        }
+
+        self.route = kept.into_iter().chain(new_route).collect();
+        self.route_index = 0;
+        Some(Reroute {
+            vehicle_id: self.id,
+            reason,
+            old_travel_time,
+            new_travel_time,
+        })
+    }
+
+    // Plan the fastest route from where the vehicle stands to the destination.
+    // Between two points it drives on along the road it stands on, picking
+    // the direction (on two-way roads) that gets it there first. False when
+    // there is no route or the vehicle isn't on a road.
+    pub fn plan_route(&mut self, network: &RoadNetwork) -> bool {
+        let Some(finish) = network.point_at(self.destination) else {
+            return false;
+        };
+
+        let rounded = self.rounded_position();
+        let on_point = (self.current_position.0 - rounded.0 as f32).abs() < 1e-3
+            && (self.current_position.1 - rounded.1 as f32).abs() < 1e-3;
+        let start = network.point_at(rounded).filter(|_| on_point);
+        let planned = match start {
+            Some(start) => routing::plan_route(network, start, finish).map(|route| (route, 0.0)),
+            None => {
+                let mut best: Option<(f32, Vec<usize>, f32)> = None;
+                for (edge, progress) in network.locate(self.current_position) {
+                    if network.edges[edge].closed {
+                        continue;
+                    }
+                    let Some(rest) = routing::plan_route(network, network.edges[edge].to, finish) else {
+                        continue;
+                    };
+                    let left = &network.edges[edge];
+                    let time = (left.length - progress) / left.speed_limit
+                        + rest.iter().map(|&e| routing::free_flow_time(network, e)).sum::<f32>();
+                    if best.as_ref().is_none_or(|(best_time, _, _)| time < *best_time) {
+                        best = Some((time, std::iter::once(edge).chain(rest).collect(), progress));
+                    }
+                }
+                best.map(|(_, route, progress)| (route, progress))
+            },
+        };
+
+        match planned {
+            Some((route, progress)) => {
+                self.route = route;
+                self.route_index = 0;
+                self.edge_progress = progress;
+                true
+            },
+            None => false,
+        }
+    }
+
+    // Time this vehicle needs for an edge at its own top speed
+    pub fn free_flow_time(&self, network: &RoadNetwork, edge: usize) -> f32 {
+        let edge = &network.edges[edge];
+        edge.length / self.max_speed.min(edge.speed_limit)
+    }
+
+    // Cell the vehicle holds, None once it has left the roads
+    pub fn cell(&self) -> Option<Cell> {
+        self.route.get(self.route_index)
+            .map(|&edge| (edge, self.lane, traffic::cell_index(self.edge_progress)))
+    }
+
+    // Edges still ahead, starting with the one the vehicle is on
+    pub fn remaining_route(&self) -> &[usize] {
+        &self.route[self.route_index.min(self.route.len())..]
+    }
+
+    // Whether the vehicle is at its destination (or gave up the trip)
+    pub fn has_arrived(&self) -> bool {
+        self.current_position == (self.destination.0 as f32, self.destination.1 as f32)
+    }
+
+    // Current position on the unit grid
+    pub fn rounded_position(&self) -> (i32, i32) {
+        (self.current_position.0.round() as i32, self.current_position.1.round() as i32)
     }
 
-}
\ No newline at end of file
+    // Current position (rounded) followed by every point still to pass
+    pub fn remaining_points(&self, network: &RoadNetwork) -> Vec<(i32, i32)> {
+        let mut points = vec![self.rounded_position()];
+        for &edge in self.remaining_route() {
+            points.push(network.position(network.edges[edge].to));
+        }
+        points
+    }
+
+    // Position along the current edge
+    fn position_on_route(&self, network: &RoadNetwork) -> (f32, f32) {
+        match self.route.get(self.route_index) {
+            Some(&edge) => {
+                let edge = &network.edges[edge];
+                let (ax, ay) = network.position(edge.from);
+                let (bx, by) = network.position(edge.to);
+                let t = self.edge_progress / edge.length;
+                (
+                    ax as f32 + (bx - ax) as f32 * t,
+                    ay as f32 + (by - ay) as f32 * t,
+                )
+            },
+            // Route finished
+            None => (self.destination.0 as f32, self.destination.1 as f32),
+        }
+    }
+}
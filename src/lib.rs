@@ -0,0 +1,12 @@
+// lib.rs
+// The simulation engine, used by the binary, the benchmarks and the tests
+pub mod builder;
+pub mod event_log;
+pub mod helpers;
+pub mod render;
+pub mod scenario;
+pub mod simulation;
+pub mod snapshot;
+
+pub use builder::{BuildError, SimulationBuilder};
+pub use simulation::Simulation;
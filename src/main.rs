@@ -1,79 +1,278 @@
-use tokio::sync::mpsc;
-use std::time::Instant;
-use helpers::{analyzer::run_analyzer, message::SimulationMessage,variables::{GRID_HEIGHT, GRID_WIDTH}, grid::Grid, vehicle::Vehicle};
+use clap::{Args, Parser, Subcommand};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use engine::event_log::Replay;
+use engine::helpers::analyzer::run_analyzer;
+use engine::helpers::config::{SimulationConfig, TimingConfig};
+use engine::helpers::control::{self, Jump};
+use engine::helpers::log::{self, LogLevel};
+use engine::render::TerminalRenderer;
+use engine::scenario::Scenario;
+use engine::SimulationBuilder;
 
-mod helpers;
+#[derive(Parser)]
+#[command(version, about = "Traffic simulation on a grid or a loaded road network")]
+struct Cli {
+    // Interactive run with the default settings when left out
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    #[command(about = "Draw the simulation in the terminal, controlled from the keyboard")]
+    Run(RunArgs),
+    #[command(about = "Run as fast as possible without drawing and print a summary")]
+    Headless(RunArgs),
+    #[command(about = "Show a run recorded with an event log again, going back and forth in it")]
+    Replay {
+        #[arg(help = "Event log written by a run (run.event_log)")]
+        log: PathBuf,
+        #[arg(long, value_name = "MS", help = "Wall-clock time between two ticks")]
+        tick_interval: Option<u64>,
+    },
+    #[command(about = "Check a config, its network and its scenario without running them")]
+    ValidateConfig(RunArgs),
+}
+
+// Settings of the config file that can be overridden for a single run
+#[derive(Args, Default)]
+struct RunArgs {
+    #[arg(help = "Config file (.toml or .json), the defaults when left out")]
+    config: Option<PathBuf>,
+    #[arg(long, value_name = "ROWSxCOLUMNS", value_parser = parse_grid_size, help = "Generate a grid of this size, e.g. 4x6")]
+    grid: Option<(i32, i32)>,
+    #[arg(long, value_name = "MS", help = "Wall-clock time between two ticks, 0 runs them back to back")]
+    tick_interval: Option<u64>,
+    #[arg(long, help = "Seed of the random number generator")]
+    seed: Option<u64>,
+    #[arg(long, value_name = "SECONDS", help = "Stop after this many simulated seconds")]
+    duration: Option<f64>,
+    #[arg(long, help = "Stop after this many ticks")]
+    ticks: Option<u64>,
+    #[arg(long, value_name = "FILE", help = "Scenario to run instead of random traffic")]
+    scenario: Option<String>,
+    #[arg(long, value_name = "DIR", help = "Directory for the files the run writes and its summary")]
+    output_dir: Option<String>,
+    #[arg(long, value_name = "LEVEL", help = "error, warn, info or debug (every analyzer message)")]
+    log_level: Option<LogLevel>,
+}
 
 #[tokio::main]
 async fn main() {
-    // Set up a channel for inter-component communication
-    let (tx, rx) = mpsc::channel::<SimulationMessage>(100);
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Some(Command::Run(args)) => match load_config(&args, false) {
+            Ok(config) => run(config).await,
+            Err(e) => Err(e),
+        },
+        Some(Command::Headless(args)) => match load_config(&args, true) {
+            Ok(config) => run(config).await,
+            Err(e) => Err(e),
+        },
+        Some(Command::Replay { log, tick_interval }) => replay(&log, tick_interval).await,
+        Some(Command::ValidateConfig(args)) => validate(&args),
+        None => run(SimulationConfig::default()).await,
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
 
-    // Spawn the dummy analyzer task
-    tokio::spawn(async move {
-        run_analyzer(rx).await;
-    });
+// Config file with the flags applied on top
+fn load_config(args: &RunArgs, headless: bool) -> Result<SimulationConfig, String> {
+    let mut config = match &args.config {
+        Some(path) => SimulationConfig::read(path).map_err(|e| e.to_string())?,
+        None => SimulationConfig::default(),
+    };
+    config.run.headless = headless;
+    if let Some((height, width)) = args.grid {
+        config.grid.height = height;
+        config.grid.width = width;
+        config.grid.network_file = None;
+        config.grid.osm_file = None;
+    }
+    if let Some(tick_interval) = args.tick_interval {
+        config.simulation.tick_interval_ms = tick_interval;
+    }
+    if let Some(seed) = args.seed {
+        config.simulation.seed = Some(seed);
+    }
+    if let Some(duration) = args.duration {
+        config.run.until_time = Some(duration);
+    }
+    if let Some(ticks) = args.ticks {
+        config.run.ticks = Some(ticks);
+    }
+    if let Some(scenario) = &args.scenario {
+        config.run.scenario = Some(scenario.clone());
+    }
+    if let Some(output_dir) = &args.output_dir {
+        config.run.output_dir = Some(output_dir.clone());
+    }
+    if let Some(log_level) = args.log_level {
+        config.run.log_level = log_level;
+    }
+    config.validate().map_err(|e| e.to_string())?;
+    Ok(config)
+}
+
+// Builder for the config, with its scenario, snapshot and event log
+fn builder_for(config: SimulationConfig) -> Result<SimulationBuilder, String> {
+    let run = config.run.clone();
+    let mut builder = SimulationBuilder::from_config(config);
+    if let Some(path) = &run.resume_from {
+        builder = builder.resume_from(path);
+    }
+    if let Some(path) = &run.event_log {
+        builder = builder.event_log(path);
+    }
+    if let Some(path) = &run.scenario {
+        let scenario = Scenario::load(Path::new(path)).map_err(|e| e.to_string())?;
+        builder = builder.scenario(scenario);
+    }
+    Ok(builder)
+}
 
-    // Interval for more consistent scheduling
-    // It calculates next tick based on the initial start time
-    let mut interval = tokio::time::interval(std::time::Duration::from_millis(300));
+fn validate(args: &RunArgs) -> Result<(), String> {
+    let mut config = load_config(args, false)?;
+    // Build the network and scenario, but write nothing
+    config.run.event_log = None;
+    builder_for(config)?.build().map_err(|e| e.to_string())?;
+    let name = args.config.as_deref().unwrap_or(Path::new("default config"));
+    println!("{}: ok", name.display());
+    Ok(())
+}
 
-    // Track time passed
-    let mut last_update = Instant::now();
+async fn run(mut config: SimulationConfig) -> Result<(), String> {
+    log::set_level(config.run.log_level);
+    let output_dir = config.run.output_dir.clone().map(PathBuf::from);
+    if let Some(dir) = &output_dir {
+        std::fs::create_dir_all(dir).map_err(|e| format!("could not create {}: {}", dir.display(), e))?;
+        // Relative paths of written files end up in the output directory
+        let in_dir = |path: &mut Option<String>| {
+            if let Some(file) = path {
+                *file = dir.join(&*file).display().to_string();
+            }
+        };
+        in_dir(&mut config.run.event_log);
+        in_dir(&mut config.run.save_snapshot);
+        in_dir(&mut config.grid.export_network);
+    }
 
-    // Generate height by width grid of cells
-    let mut grid = Grid::generate_grid(Grid::new(), GRID_HEIGHT, GRID_WIDTH);
-    print!("{}", grid);
+    // Headless runs skip drawing and pacing and only print a summary at the end
+    let headless = config.run.headless;
 
-    let mut tick: u64 = 0;
-    loop {
-        // Calculate how much time passed
-        let now = Instant::now();
-        let time_passed = (now - last_update).as_secs_f32();
-        last_update = now;
-        tick += 1;
-
-        // Clear the screen and put the cursor at first row & first col of the screen
-        print!("\x1B[2J\x1B[1;1H");
-
-        // Update Traffic Lights asynchronously
-        grid.update_traffic_lights(time_passed).await;
-
-        // Generate more vehicles asynchronously
-        let mut handles = vec![];
-        for _ in 0..GRID_WIDTH {
-            let handle = tokio::spawn(
-                Vehicle::generate_vehicle()
-            );
-            handles.push(handle);
+    // Interval for more consistent scheduling, scaled by the speed
+    // multiplier and paused/stepped from the keyboard.
+    // Pacing only, the simulation itself runs on its own clock.
+    let pace = (!headless && config.simulation.tick_interval_ms > 0)
+        .then(|| Duration::from_millis(config.simulation.tick_interval_ms));
+
+    let save_snapshot = config.run.save_snapshot.clone();
+    let mut builder = builder_for(config)?;
+    if !headless {
+        builder = builder.renderer(TerminalRenderer);
+    }
+    let mut simulation = builder.build().map_err(|e| e.to_string())?;
+    if let Some(path) = &simulation.config.grid.export_network {
+        if let Err(e) = simulation.grid.save_network(Path::new(path)) {
+            eprintln!("{}", e);
         }
-        // Collect generated vehicles
-        for handle in handles {
-            match handle.await {
-                Ok(vehicle) => grid.vehicles.push(vehicle),
-                Err(e) => {
-                    eprintln!("Error generating vehicle: {}", e);
-                }
-            }
+    }
+
+    // Spawn the analyzer task, it hands back the run's totals once the channel closes
+    let rx = simulation.messages();
+    let verbose = log::enabled(LogLevel::Debug);
+    let analyzer = tokio::spawn(async move {
+        run_analyzer(rx, verbose).await
+    });
+
+    let (mut run_control, control_tx) = control::RunControl::new(pace);
+    if headless {
+        // No keyboard in batch runs
+        drop(control_tx);
+    } else {
+        control::spawn_keyboard_reader(control_tx);
+        print!("{}", simulation.grid);
+    }
+    let started = Instant::now();
+
+    // Stop at whichever configured limit comes first
+    while !simulation.is_finished() {
+        // The renderer draws the grid at the end of the step
+        simulation.step().await;
+        if !headless {
+            println!("{} | {}", run_control.status(), control::KEY_HELP);
         }
 
-        // Send a simulation update message to the analyzer as a placeholder
-        let update_message = SimulationMessage::GridUpdate {
-            tick,
-            vehicle_count: grid.vehicles.len(),
-            light_count: grid.traffic_lights.len(),
-        };
-        if let Err(e) = tx.send(update_message).await {
-            eprintln!("Failed to send simulation update: {}", e);
+        // Wait for the next tick (non-blocking), or for a command while paused
+        run_control.next_tick().await;
+    }
+
+    if let Some(path) = &save_snapshot {
+        match simulation.save_snapshot(Path::new(path)) {
+            Ok(()) => log::info(format!("Snapshot saved to {}", path)),
+            Err(e) => eprintln!("{}", e),
         }
+    }
+
+    // Closing the channel lets the analyzer finish
+    simulation.close_messages();
+    let summary = analyzer.await.map_err(|e| format!("Analyzer task failed: {}", e))?;
+    let report = format!(
+        "Seed:             {}\n{}Wall time:        {:.2} s\n",
+        simulation.seed(),
+        summary,
+        started.elapsed().as_secs_f32(),
+    );
+    print!("{}", report);
+    if let Some(dir) = &output_dir {
+        let path = dir.join("summary.txt");
+        match std::fs::write(&path, &report) {
+            Ok(()) => log::info(format!("Summary saved to {}", path.display())),
+            Err(e) => eprintln!("could not write {}: {}", path.display(), e),
+        }
+    }
+    Ok(())
+}
 
-        // Update vehicle positions 
-        grid.update_vehicles().await;
-        
-        // Then print the updated grid
-        print!("{}", grid);
-        
-        // Wait for the next tick (non-blocking)
-        interval.tick().await;
+// Step through a logged run with the same keyboard controls as a live one
+async fn replay(path: &Path, tick_interval: Option<u64>) -> Result<(), String> {
+    let mut replay = Replay::open(path).map_err(|e| e.to_string())?;
+    let renderer = TerminalRenderer;
+    let tick_interval = tick_interval.unwrap_or(TimingConfig::default().tick_interval_ms);
+    let pace = (tick_interval > 0).then(|| Duration::from_millis(tick_interval));
+    let (mut run_control, control_tx) = control::RunControl::new(pace);
+    run_control.seekable = true;
+    control::spawn_keyboard_reader(control_tx);
+
+    loop {
+        renderer.draw(&format!("Replay tick {} (seed {})", replay.tick, replay.seed), &replay.grid);
+        println!("{} | {}", run_control.status(), control::REPLAY_KEY_HELP);
+        run_control.next_tick().await;
+        let target = match run_control.jump.take() {
+            Some(Jump::Back(ticks)) => replay.tick.saturating_sub(ticks),
+            Some(Jump::To(tick)) => tick,
+            None => {
+                if !replay.next_tick().map_err(|e| e.to_string())? {
+                    break;
+                }
+                continue;
+            },
+        };
+        // Paused where the jump lands, the end of the log included
+        replay.seek(target).map_err(|e| e.to_string())?;
     }
-}
\ No newline at end of file
+    println!("End of the log at tick {} ({:.1} s)", replay.tick, replay.tick as f64 * replay.dt as f64);
+    Ok(())
+}
+
+// "4x6" as 4 rows and 6 columns
+fn parse_grid_size(value: &str) -> Result<(i32, i32), String> {
+    let (rows, columns) = value.split_once(['x', 'X'])
+        .ok_or_else(|| format!("expected ROWSxCOLUMNS, got `{}`", value))?;
+    let parse = |number: &str| number.trim().parse::<i32>().map_err(|e| format!("`{}`: {}", number, e));
+    Ok((parse(rows)?, parse(columns)?))
+}
@@ -0,0 +1,31 @@
+// render.rs
+use crate::helpers::grid::Grid;
+use crate::Simulation;
+
+// Draws the simulation after every tick. Send like the analyzers, so
+// simulations can run on spawned tasks.
+pub trait Renderer: Send {
+    fn render(&mut self, simulation: &Simulation);
+}
+
+// Redraws the grid and the vehicle routes in the terminal
+#[derive(Default)]
+pub struct TerminalRenderer;
+
+impl TerminalRenderer {
+    // Also used to show replayed runs, which have a grid but no simulation
+    pub fn draw(&self, title: &str, grid: &Grid) {
+        // Clear the screen and put the cursor at first row & first col of the screen
+        print!("\x1B[2J\x1B[1;1H");
+        println!("{}", title);
+        // Then print the updated grid and where vehicles are heading
+        print!("{}", grid);
+        print!("{}", grid.routes());
+    }
+}
+
+impl Renderer for TerminalRenderer {
+    fn render(&mut self, simulation: &Simulation) {
+        self.draw(&format!("Tick {} (seed {})", simulation.tick(), simulation.seed()), &simulation.grid);
+    }
+}
@@ -0,0 +1,220 @@
+// scenario.rs
+use crate::helpers::config::{ConfigError, GridConfig, VehicleProfiles};
+use crate::helpers::grid::Grid;
+use crate::helpers::light::SignalPlan;
+use crate::helpers::network::RoadNetwork;
+use crate::helpers::routing;
+use crate::helpers::vehicle::{Vehicle, VehicleType};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::Path;
+
+// A prepared run instead of random traffic: the network, the vehicles on it
+// at the start, signal plans and a timeline of events, e.g.
+//
+//     [grid]
+//     height = 3
+//     width = 3
+//
+//     [[vehicles]]
+//     type = "bus"
+//     from = [0, 0]
+//     to = [20, 20]
+//
+//     [[timeline]]
+//     time = 120.0
+//     action = "close_edge"
+//     from = [0, 0]
+//     to = [10, 0]
+//
+// Everything else (timing, vehicle profiles, seed) comes from the config.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scenario {
+    // Generated or loaded the same way as the grid of the config
+    pub grid: GridConfig,
+    pub vehicles: Vec<ScenarioVehicle>,
+    pub signal_plans: Vec<ScenarioSignalPlan>,
+    pub timeline: Vec<TimelineEvent>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScenarioVehicle {
+    #[serde(rename = "type")]
+    pub vehicle_type: VehicleType,
+    // Points of the network
+    pub from: (i32, i32),
+    pub to: (i32, i32),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScenarioSignalPlan {
+    pub position: (i32, i32),
+    // In seconds:
+    pub green_duration: f32,
+    pub yellow_duration: f32,
+    pub red_duration: f32,
+    #[serde(default)]
+    pub offset: f32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TimelineEvent {
+    // Simulated seconds since the start
+    pub time: f64,
+    #[serde(flatten)]
+    pub action: TimelineAction,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case", deny_unknown_fields)]
+pub enum TimelineAction {
+    // The road from one point to the next, in that direction only
+    CloseEdge { from: (i32, i32), to: (i32, i32) },
+    OpenEdge { from: (i32, i32), to: (i32, i32) },
+    // Vehicles queue at the point and enter one by one as the road frees up.
+    // Without `to` every one of them picks a random reachable destination.
+    Spawn {
+        count: u32,
+        #[serde(rename = "type")]
+        vehicle_type: VehicleType,
+        from: (i32, i32),
+        #[serde(default)]
+        to: Option<(i32, i32)>,
+    },
+}
+
+// Progress of a scenario during the run, kept in snapshots
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScenarioRun {
+    // Sorted by time, events before `next` are done
+    pub timeline: Vec<TimelineEvent>,
+    pub next: usize,
+    // Created but still waiting for room at their start
+    pub waiting: VecDeque<Vehicle>,
+    // On the grid but not announced to the analyzer yet
+    pub unannounced: Vec<u64>,
+}
+
+impl Scenario {
+    // Load a scenario from a .toml or .json file
+    pub fn load(path: &Path) -> Result<Scenario, ConfigError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| ConfigError::Io(format!("{}: {}", path.display(), e)))?;
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(&contents).map_err(|e| ConfigError::Parse(e.to_string())),
+            Some("json") => serde_json::from_str(&contents).map_err(|e| ConfigError::Parse(e.to_string())),
+            _ => Err(ConfigError::Parse(format!("{}: expected a .toml or .json file", path.display()))),
+        }
+    }
+
+    pub fn plans(&self) -> Vec<((i32, i32), SignalPlan)> {
+        self.signal_plans.iter()
+            .map(|plan| {
+                (plan.position, SignalPlan {
+                    green_duration: plan.green_duration,
+                    yellow_duration: plan.yellow_duration,
+                    red_duration: plan.red_duration,
+                    offset: plan.offset,
+                })
+            })
+            .collect()
+    }
+
+    // Check the scenario against the network and put its vehicles on the
+    // grid, numbered from `next_id` on. Reports the first problem.
+    pub fn start(
+        &self,
+        grid: &mut Grid,
+        profiles: &VehicleProfiles,
+        next_id: &mut u64,
+    ) -> Result<ScenarioRun, String> {
+        let network = &grid.network;
+        for event in &self.timeline {
+            if !event.time.is_finite() || event.time < 0.0 {
+                return Err(format!("timeline event at {}: time must be a number of seconds from 0 on", event.time));
+            }
+            match &event.action {
+                TimelineAction::CloseEdge { from, to } | TimelineAction::OpenEdge { from, to } => {
+                    edge_at(network, *from, *to)?;
+                },
+                TimelineAction::Spawn { count, from, to, .. } => {
+                    if *count == 0 {
+                        return Err(format!("timeline event at {}: count must be at least 1", event.time));
+                    }
+                    point(network, *from)?;
+                    if let Some(to) = to {
+                        point(network, *to)?;
+                    }
+                },
+            }
+        }
+
+        let mut waiting = VecDeque::new();
+        for planned in &self.vehicles {
+            let start = point(network, planned.from)?;
+            let finish = point(network, planned.to)?;
+            let route = routing::plan_route(network, start, finish)
+                .ok_or_else(|| format!("vehicle from {:?} to {:?}: no route", planned.from, planned.to))?;
+            let mut vehicle = Vehicle::with_profile(*next_id, planned.vehicle_type.clone(), planned.from, planned.to, profiles);
+            vehicle.route = route;
+            *next_id += 1;
+            waiting.push_back(vehicle);
+        }
+
+        let mut timeline = self.timeline.clone();
+        // Stable, so events at the same time keep their order
+        timeline.sort_by(|a, b| a.time.total_cmp(&b.time));
+        let mut run = ScenarioRun {
+            timeline,
+            next: 0,
+            waiting,
+            unannounced: Vec::new(),
+        };
+        run.admit(grid);
+        Ok(run)
+    }
+}
+
+impl ScenarioRun {
+    // Timeline events due at the given time, each returned once
+    pub fn due(&mut self, time: f64) -> Vec<TimelineAction> {
+        let mut actions = Vec::new();
+        while let Some(event) = self.timeline.get(self.next) {
+            if event.time > time {
+                break;
+            }
+            actions.push(event.action.clone());
+            self.next += 1;
+        }
+        actions
+    }
+
+    // Move the waiting vehicles that have room onto the grid, in order
+    pub fn admit(&mut self, grid: &mut Grid) {
+        let mut blocked = VecDeque::new();
+        while let Some(vehicle) = self.waiting.pop_front() {
+            let id = vehicle.id;
+            if grid.add_vehicle(vehicle.clone()) {
+                self.unannounced.push(id);
+            } else {
+                blocked.push_back(vehicle);
+            }
+        }
+        self.waiting = blocked;
+    }
+}
+
+// Index of the point at a position
+pub fn point(network: &RoadNetwork, position: (i32, i32)) -> Result<usize, String> {
+    network.point_at(position).ok_or_else(|| format!("no point at {:?}", position))
+}
+
+// Index of the edge between two positions
+pub fn edge_at(network: &RoadNetwork, from: (i32, i32), to: (i32, i32)) -> Result<usize, String> {
+    network.edge_between(point(network, from)?, point(network, to)?)
+        .ok_or_else(|| format!("no road from {:?} to {:?}", from, to))
+}
@@ -0,0 +1,377 @@
+// simulation.rs
+use crate::builder::{BuildError, SimulationBuilder};
+use crate::event_log::EventLog;
+use crate::helpers::analyzer::Analyzer;
+use crate::helpers::boundary::Boundary;
+use crate::helpers::clock::SimulationClock;
+use crate::helpers::config::SimulationConfig;
+use crate::helpers::demand::Demand;
+use crate::helpers::grid::Grid;
+use crate::helpers::log;
+use crate::helpers::message::SimulationMessage;
+use crate::helpers::vehicle::{Vehicle, VehicleType};
+use crate::helpers::routing;
+use crate::render::Renderer;
+use crate::scenario::{self, ScenarioRun, TimelineAction};
+use crate::snapshot::{Snapshot, SnapshotError};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+// A whole simulation run: the grid with its vehicles and lights, the clock,
+// the random number generator and the channel to the analyzer
+pub struct Simulation {
+    pub config: SimulationConfig,
+    pub grid: Grid,
+    pub clock: SimulationClock,
+    // Every random choice of the run comes from this generator
+    pub(crate) rng: ChaCha8Rng,
+    pub(crate) seed: u64,
+    // Id of the next spawned vehicle
+    pub(crate) next_vehicle_id: u64,
+    // Set once someone listens to the messages
+    tx: Option<mpsc::Sender<SimulationMessage>>,
+    // Called with every message, and after every tick
+    pub(crate) analyzers: Vec<Box<dyn Analyzer>>,
+    pub(crate) renderers: Vec<Box<dyn Renderer>>,
+    // Written after every tick when set
+    pub(crate) event_log: Option<EventLog>,
+    // Timeline that replaces the spawn policy when a scenario is loaded
+    pub(crate) scenario: Option<ScenarioRun>,
+    // Trips of the OD matrix of the od_matrix spawn policy
+    pub(crate) demand: Option<Demand>,
+    // Sources, sinks and entry queues of an open network
+    pub(crate) boundary: Option<Boundary>,
+}
+
+impl Simulation {
+    // Simulation set up from a config, see SimulationBuilder for more options
+    pub fn new(config: SimulationConfig) -> Result<Simulation, BuildError> {
+        SimulationBuilder::from_config(config).build()
+    }
+
+    pub(crate) fn with_grid(config: SimulationConfig, grid: Grid, rng: ChaCha8Rng, seed: u64) -> Simulation {
+        Simulation {
+            clock: SimulationClock::new(config.simulation.dt),
+            config,
+            grid,
+            rng,
+            seed,
+            next_vehicle_id: 0,
+            tx: None,
+            analyzers: Vec::new(),
+            renderers: Vec::new(),
+            event_log: None,
+            scenario: None,
+            demand: None,
+            boundary: None,
+        }
+    }
+
+    // Channel of the messages sent from now on. Without a receiver
+    // the messages are dropped.
+    pub fn messages(&mut self) -> mpsc::Receiver<SimulationMessage> {
+        let (tx, rx) = mpsc::channel(self.config.simulation.channel_capacity);
+        self.tx = Some(tx);
+        rx
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.clock.tick
+    }
+
+    // Simulated seconds since the start
+    pub fn time(&self) -> f64 {
+        self.clock.time()
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    // Whether the run reached run.ticks or run.until_time
+    pub fn is_finished(&self) -> bool {
+        let ticks_done = self.config.run.ticks.is_some_and(|ticks| self.clock.tick >= ticks);
+        let time_done = self.config.run.until_time.is_some_and(|time| self.clock.time() >= time);
+        ticks_done || time_done
+    }
+
+    // Run ticks until the given simulated time
+    pub async fn run_until(&mut self, time: f64) {
+        while self.clock.time() < time {
+            self.step().await;
+        }
+    }
+
+    // Advance the simulation by one tick
+    pub async fn step(&mut self) {
+        self.clock.advance();
+        let tick = self.clock.tick;
+        let dt = self.clock.dt;
+
+        // Update Traffic Lights asynchronously
+        self.grid.update_traffic_lights(dt).await;
+
+        // Scenarios bring their own vehicles
+        let mut arrivals = match (&self.scenario, &self.demand) {
+            (Some(_), _) => Vec::new(),
+            (None, Some(demand)) => demand.arrivals(dt as f64, &mut self.rng),
+            (None, None) => {
+                let sources = self.boundary.as_ref().map(|boundary| boundary.sources.as_slice());
+                self.config.spawn.policy.arrivals(self.clock.time(), dt as f64, &self.grid.network, sources, &mut self.rng)
+            },
+        };
+
+        // Vehicles waiting outside an open network go first, new ones
+        // enter at its sources
+        let mut route_messages = vec![];
+        if let Some(boundary) = &mut self.boundary {
+            let admitted = boundary.admit(&mut self.grid);
+            route_messages.extend(self.announce(tick, admitted));
+        }
+        // Those still waiting count against the limit too, they are on
+        // the roads as soon as their entry has room
+        if let Some(max_vehicles) = self.config.spawn.max_vehicles {
+            let queued = self.boundary.as_ref().map_or(0, |boundary| boundary.queued());
+            arrivals.truncate(max_vehicles.saturating_sub(self.grid.vehicles.len() + queued));
+        }
+        if let Some(boundary) = &self.boundary {
+            arrivals.retain_mut(|arrival| boundary.fill(arrival, &mut self.rng));
+        }
+
+        // Generate more vehicles asynchronously
+        let mut handles = vec![];
+        for arrival in arrivals {
+            // Ids and generators are handed out in order before the tasks run
+            let id = self.next_vehicle_id;
+            self.next_vehicle_id += 1;
+            let vehicle_rng = ChaCha8Rng::from_rng(&mut self.rng);
+            let handle = tokio::spawn(
+                Vehicle::generate_vehicle(id, Arc::clone(&self.grid.network), self.config.vehicles.clone(), arrival, vehicle_rng)
+            );
+            handles.push(handle);
+        }
+        // Collect generated vehicles
+        for handle in handles {
+            match handle.await {
+                // Nowhere to go from its start, the arrival is dropped
+                Ok(None) => {},
+                Ok(Some(vehicle)) => {
+                    let message = SimulationMessage::RoutePlanned {
+                        tick,
+                        vehicle_id: vehicle.id,
+                        route: vehicle.remaining_points(&self.grid.network),
+                    };
+                    // Skipped when its start is blocked by queued vehicles,
+                    // unless it can wait outside an open network
+                    let entered = match &mut self.boundary {
+                        Some(boundary) => boundary.enter(vehicle, &mut self.grid),
+                        None => self.grid.add_vehicle(vehicle),
+                    };
+                    if entered {
+                        route_messages.push(message);
+                    }
+                },
+                Err(e) => {
+                    eprintln!("Error generating vehicle: {}", e);
+                }
+            }
+        }
+
+        if self.scenario.is_some() {
+            route_messages.extend(self.run_scenario(tick));
+        }
+        // Trips start once the vehicle is on the grid, whichever way it got there
+        for vehicle in &mut self.grid.vehicles {
+            vehicle.trip.spawn_tick.get_or_insert(tick);
+        }
+
+        // Send a simulation update message to the analyzer
+        self.send(SimulationMessage::GridUpdate {
+            tick,
+            time: self.clock.time(),
+            vehicle_count: self.grid.vehicles.len(),
+            light_count: self.grid.traffic_lights.len(),
+            average_speed: self.grid.average_speed(),
+            queued: self.boundary.as_ref().map_or(0, |boundary| boundary.queued()),
+        }).await;
+        for message in route_messages {
+            self.send(message).await;
+        }
+
+        // Update vehicle positions
+        let updates = self.grid.update_vehicles(dt).await;
+        for reroute in updates.reroutes {
+            self.send(SimulationMessage::Rerouted {
+                tick,
+                vehicle_id: reroute.vehicle_id,
+                reason: reroute.reason,
+                old_travel_time: reroute.old_travel_time,
+                new_travel_time: reroute.new_travel_time,
+            }).await;
+        }
+        for vehicle in updates.arrived {
+            self.send(trip_completed(tick, dt, vehicle)).await;
+        }
+
+        // Optional check that the vehicles never overlap
+        if self.config.simulation.check_occupancy {
+            if let Err(e) = self.grid.check_occupancy() {
+                log::warn(format!("Occupancy check failed: {}", e));
+            }
+        }
+
+        if let Some(event_log) = &mut self.event_log {
+            if let Err(e) = event_log.record(tick, &self.grid) {
+                // Keep running, only without the log
+                log::warn(e);
+                self.event_log = None;
+            }
+        }
+
+        // Renderers get the simulation itself, so they are taken out meanwhile
+        let mut renderers = std::mem::take(&mut self.renderers);
+        for renderer in &mut renderers {
+            renderer.render(self);
+        }
+        self.renderers = renderers;
+    }
+
+    // Write the whole state to a file to resume from later,
+    // see SimulationBuilder::resume_from
+    pub fn save_snapshot(&self, path: &Path) -> Result<(), SnapshotError> {
+        Snapshot::capture(self).save(path)
+    }
+
+    // Carry out the timeline events that are due and let waiting vehicles
+    // enter, returning the routes of the vehicles that did
+    fn run_scenario(&mut self, tick: u64) -> Vec<SimulationMessage> {
+        let Some(mut run) = self.scenario.take() else {
+            return Vec::new();
+        };
+        for action in run.due(self.clock.time()) {
+            match action {
+                TimelineAction::CloseEdge { from, to } | TimelineAction::OpenEdge { from, to } => {
+                    let closed = matches!(action, TimelineAction::CloseEdge { .. });
+                    match scenario::edge_at(&self.grid.network, from, to) {
+                        Ok(edge) => self.grid.set_edge_closed(edge, closed),
+                        Err(e) => log::warn(format!("Scenario event skipped: {}", e)),
+                    }
+                },
+                TimelineAction::Spawn { count, vehicle_type, from, to } => {
+                    for _ in 0..count {
+                        match self.scenario_vehicle(vehicle_type.clone(), from, to) {
+                            Ok(vehicle) => run.waiting.push_back(vehicle),
+                            Err(e) => log::warn(format!("Scenario vehicle skipped: {}", e)),
+                        }
+                    }
+                },
+            }
+        }
+        run.admit(&mut self.grid);
+
+        let messages = self.announce(tick, std::mem::take(&mut run.unannounced));
+        self.scenario = Some(run);
+        messages
+    }
+
+    // Routes of vehicles that just entered the grid
+    fn announce(&self, tick: u64, ids: Vec<u64>) -> Vec<SimulationMessage> {
+        ids.into_iter()
+            .filter_map(|id| self.grid.vehicles.iter().find(|vehicle| vehicle.id == id))
+            .map(|vehicle| SimulationMessage::RoutePlanned {
+                tick,
+                vehicle_id: vehicle.id,
+                route: vehicle.remaining_points(&self.grid.network),
+            })
+            .collect()
+    }
+
+    // Vehicle of a spawn event, heading to a random reachable point
+    // when the event gives no destination
+    fn scenario_vehicle(
+        &mut self,
+        vehicle_type: VehicleType,
+        from: (i32, i32),
+        to: Option<(i32, i32)>,
+    ) -> Result<Vehicle, String> {
+        let network = Arc::clone(&self.grid.network);
+        let start = scenario::point(&network, from)?;
+        let (finish, route) = match to {
+            Some(to) => {
+                let finish = scenario::point(&network, to)?;
+                let route = routing::plan_route(&network, start, finish)
+                    .ok_or_else(|| format!("no route from {:?} to {:?}", from, to))?;
+                (finish, route)
+            },
+            None => (0..10)
+                .find_map(|_| {
+                    let candidate = self.rng.random_range(0..network.points.len());
+                    routing::plan_route(&network, start, candidate)
+                        .filter(|route| !route.is_empty())
+                        .map(|route| (candidate, route))
+                })
+                .ok_or_else(|| format!("no destination reachable from {:?}", from))?,
+        };
+
+        let mut vehicle = Vehicle::with_profile(
+            self.next_vehicle_id,
+            vehicle_type,
+            from,
+            network.position(finish),
+            &self.config.vehicles,
+        );
+        vehicle.route = route;
+        self.next_vehicle_id += 1;
+        Ok(vehicle)
+    }
+
+    // Results of the analyzers added with the builder
+    pub fn reports(&self) -> Vec<String> {
+        self.analyzers.iter().map(|analyzer| analyzer.report()).collect()
+    }
+
+    async fn send(&mut self, message: SimulationMessage) {
+        for analyzer in &mut self.analyzers {
+            analyzer.record(&message);
+        }
+        if let Some(tx) = &self.tx {
+            if let Err(e) = tx.send(message).await {
+                // Nobody listens anymore, stop sending
+                log::warn(format!("Failed to send simulation update: {}", e));
+                self.tx = None;
+            }
+        }
+    }
+
+    // Close the message channel so the receiver sees the end of the run
+    pub fn close_messages(&mut self) {
+        self.tx = None;
+    }
+}
+
+// Report of a vehicle taken off the grid, at its destination or not
+fn trip_completed(tick: u64, dt: f32, vehicle: Vehicle) -> SimulationMessage {
+    let trip = vehicle.trip;
+    let spawn_tick = trip.spawn_tick.unwrap_or(tick);
+    // Vehicles already move in the tick they enter
+    let travel_time = (tick - spawn_tick + 1) as f32 * dt;
+    SimulationMessage::TripCompleted {
+        tick,
+        vehicle_id: vehicle.id,
+        vehicle_type: vehicle.vehicle_type,
+        abandoned: trip.abandoned,
+        spawn_tick,
+        distance: trip.distance,
+        travel_time,
+        stopped_time: trip.stopped_time,
+        free_flow_time: trip.free_flow_time,
+        delay: (travel_time - trip.free_flow_time).max(0.0),
+    }
+}
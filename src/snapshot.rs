@@ -0,0 +1,100 @@
+// snapshot.rs
+use crate::helpers::boundary::Boundary;
+use crate::helpers::clock::SimulationClock;
+use crate::helpers::config::SimulationConfig;
+use crate::helpers::demand::Demand;
+use crate::helpers::grid::Grid;
+use crate::scenario::ScenarioRun;
+use crate::Simulation;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+// Everything needed to carry on a run exactly where it stopped.
+// Analyzers, renderers, the event log and the message channel are not part of it.
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    pub config: SimulationConfig,
+    pub grid: Grid,
+    pub clock: SimulationClock,
+    // Position in the random number stream, not only the seed
+    pub rng: ChaCha8Rng,
+    pub seed: u64,
+    pub next_vehicle_id: u64,
+    #[serde(default)]
+    pub scenario: Option<ScenarioRun>,
+    #[serde(default)]
+    pub demand: Option<Demand>,
+    #[serde(default)]
+    pub boundary: Option<Boundary>,
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    // The file could not be read or written
+    Io(String),
+    // The contents are not a snapshot
+    Format(String),
+}
+
+impl Snapshot {
+    // Copy the state of a simulation
+    pub fn capture(simulation: &Simulation) -> Snapshot {
+        Snapshot {
+            config: simulation.config.clone(),
+            grid: simulation.grid.clone(),
+            clock: simulation.clock.clone(),
+            rng: simulation.rng.clone(),
+            seed: simulation.seed,
+            next_vehicle_id: simulation.next_vehicle_id,
+            scenario: simulation.scenario.clone(),
+            demand: simulation.demand.clone(),
+            boundary: simulation.boundary.clone(),
+        }
+    }
+
+    // Turn the snapshot back into a simulation
+    pub fn restore(self) -> Simulation {
+        let mut simulation = Simulation::with_grid(self.config, self.grid, self.rng, self.seed);
+        simulation.clock = self.clock;
+        simulation.next_vehicle_id = self.next_vehicle_id;
+        simulation.scenario = self.scenario;
+        simulation.demand = self.demand;
+        simulation.boundary = self.boundary;
+        simulation
+    }
+
+    // JSON for .json files, MessagePack for anything else
+    pub fn save(&self, path: &Path) -> Result<(), SnapshotError> {
+        let bytes = if is_json(path) {
+            serde_json::to_vec(self).map_err(|e| SnapshotError::Format(e.to_string()))?
+        } else {
+            rmp_serde::to_vec_named(self).map_err(|e| SnapshotError::Format(e.to_string()))?
+        };
+        std::fs::write(path, bytes).map_err(|e| SnapshotError::Io(format!("{}: {}", path.display(), e)))
+    }
+
+    pub fn load(path: &Path) -> Result<Snapshot, SnapshotError> {
+        let bytes = std::fs::read(path).map_err(|e| SnapshotError::Io(format!("{}: {}", path.display(), e)))?;
+        let format_error = |e: String| SnapshotError::Format(format!("{}: {}", path.display(), e));
+        if is_json(path) {
+            serde_json::from_slice(&bytes).map_err(|e| format_error(e.to_string()))
+        } else {
+            rmp_serde::from_slice(&bytes).map_err(|e| format_error(e.to_string()))
+        }
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension().and_then(|ext| ext.to_str()) == Some("json")
+}
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotError::Io(message) => write!(f, "could not access snapshot {}", message),
+            SnapshotError::Format(message) => write!(f, "invalid snapshot {}", message),
+        }
+    }
+}
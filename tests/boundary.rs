@@ -0,0 +1,70 @@
+// boundary.rs
+use engine::helpers::config::SimulationConfig;
+use engine::{BuildError, Simulation, SimulationBuilder};
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+// A 4x4 lattice (points 10 apart) open from west to east
+const OPEN: &str = "
+[grid]
+height = 4
+width = 4
+
+[boundary]
+enabled = true
+sources = [[0, 10], [0, 20]]
+sinks = [[30, 10], [30, 20]]
+";
+
+fn build(spawn: &str) -> Result<Simulation, BuildError> {
+    let config = SimulationConfig::from_toml(&format!("{}\n[spawn]\n{}", OPEN, spawn)).unwrap();
+    SimulationBuilder::from_config(config).seed(7).build()
+}
+
+fn temp_file(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("engine-{}-{}", std::process::id(), name))
+}
+
+#[tokio::test]
+async fn vehicles_waiting_to_enter_count_against_the_limit() {
+    // Far more arrivals than the entries let in, so queues build up
+    let mut simulation = build("count = 5\nmax_vehicles = 6").unwrap();
+    for tick in 1..=200 {
+        simulation.step().await;
+        let on_roads = simulation.grid().vehicles.len();
+        assert!(on_roads <= 6, "{} vehicles on the roads at tick {}", on_roads, tick);
+    }
+}
+
+#[tokio::test]
+async fn poisson_arrivals_enter_open_networks_at_their_sources() {
+    let mut simulation = build("policy = \"poisson\"\nrate = 0.5").unwrap();
+    let network = simulation.grid().network.clone();
+    let sources = [network.point_at((0, 10)), network.point_at((0, 20))];
+    let mut seen = HashSet::new();
+    for _ in 0..100 {
+        simulation.step().await;
+        for vehicle in &simulation.grid().vehicles {
+            if seen.insert(vehicle.id) {
+                let start = network.edges[vehicle.route[0]].from;
+                assert!(sources.contains(&Some(start)), "vehicle {} entered at {:?}", vehicle.id, network.position(start));
+            }
+        }
+    }
+    assert!(!seen.is_empty());
+}
+
+#[test]
+fn spawn_points_off_the_boundary_are_rejected() {
+    let error = build("policy = \"poisson\"\nrate = 0.5\nentry_points = [[10, 10]]").err().unwrap();
+    assert!(error.to_string().contains("(10,10) is not a source"), "{}", error);
+
+    let matrices = [("(10,10)", "(30,10)", "(10,10) is not a source"), ("(0,10)", "(0,20)", "(0,20) is not a sink")];
+    for (index, (origin, destination, rejected)) in matrices.into_iter().enumerate() {
+        let path = temp_file(&format!("od-{}.csv", index));
+        std::fs::write(&path, format!("origin,destination,trips_per_hour\n\"{}\",\"{}\",60\n", origin, destination)).unwrap();
+        let error = build(&format!("policy = \"od_matrix\"\nfile = {:?}", path.to_str().unwrap())).err().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(error.to_string().contains(rejected), "{}", error);
+    }
+}
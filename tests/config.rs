@@ -0,0 +1,27 @@
+// config.rs
+use engine::helpers::config::{SimulationConfig, VehicleProfiles};
+use engine::helpers::vehicle::ReroutePolicy;
+
+#[test]
+fn partial_vehicle_profiles_keep_the_defaults_of_their_type() {
+    let config = SimulationConfig::from_toml("[vehicles.bus]\nacceleration = 0.8\n").unwrap();
+    let defaults = VehicleProfiles::default();
+    let bus = &config.vehicles.bus;
+    assert_eq!(bus.acceleration, 0.8);
+    assert_eq!(bus.speed, defaults.bus.speed);
+    assert_eq!(bus.priority, defaults.bus.priority);
+    assert_eq!(bus.braking, defaults.bus.braking);
+    assert_eq!(config.vehicles.car.acceleration, defaults.car.acceleration);
+
+    let config = SimulationConfig::from_toml("[vehicles.emergency]\nreroute = { policy = \"never\" }\n").unwrap();
+    let emergency = &config.vehicles.emergency;
+    assert!(matches!(emergency.reroute, ReroutePolicy::Never));
+    assert_eq!(emergency.acceleration, defaults.emergency.acceleration);
+    assert_eq!(emergency.braking, defaults.emergency.braking);
+}
+
+#[test]
+fn unknown_vehicle_profile_keys_are_rejected() {
+    assert!(SimulationConfig::from_toml("[vehicles.bus]\nsped = 1.0\n").is_err());
+    assert!(SimulationConfig::from_toml("[vehicles.truck]\nspeed = 1.0\n").is_err());
+}
@@ -0,0 +1,57 @@
+// demand.rs
+use engine::helpers::demand::DemandFile;
+use engine::helpers::network::RoadNetwork;
+use engine::helpers::vehicle::VehicleType;
+use engine::SimulationBuilder;
+use std::collections::BTreeMap;
+
+// Points of a 3x3 lattice, 10 apart
+fn network() -> RoadNetwork {
+    let simulation = SimulationBuilder::new().grid_size(3, 3).seed(7).build().unwrap();
+    (*simulation.grid().network).clone()
+}
+
+fn zones() -> BTreeMap<String, Vec<(i32, i32)>> {
+    BTreeMap::from([("west".to_string(), vec![(0, 0), (0, 10), (0, 20)])])
+}
+
+#[test]
+fn quoted_points_and_zones_resolve_to_points() {
+    let network = network();
+    // Quotes open a field right after the comma, spaces go inside them
+    let csv = "origin, destination, trips_per_hour, vehicle_type\n\
+               west,\"(20,20)\", 360, bus\n\
+               \"( 20 , 0 )\", west, 36,\n";
+    let demand = DemandFile::from_csv(csv).unwrap().resolve(&zones(), &network).unwrap();
+    let point = |position| network.point_at(position).unwrap();
+
+    assert_eq!(demand.pairs.len(), 2);
+    let west: Vec<usize> = zones()["west"].iter().map(|&position| point(position)).collect();
+    assert_eq!(demand.pairs[0].origins, west);
+    assert_eq!(demand.pairs[0].destinations, [point((20, 20))]);
+    assert_eq!(demand.pairs[0].rate, 0.1);
+    assert_eq!(demand.pairs[0].vehicle_type, Some(VehicleType::Bus));
+
+    assert_eq!(demand.pairs[1].origins, [point((20, 0))]);
+    assert_eq!(demand.pairs[1].destinations, west);
+    assert_eq!(demand.pairs[1].rate, 0.01);
+    assert_eq!(demand.pairs[1].vehicle_type, None);
+}
+
+#[test]
+fn bad_rows_are_rejected() {
+    let network = network();
+    let header = "origin,destination,trips_per_hour\n";
+    // The comma of an unquoted point splits it over two columns
+    assert!(DemandFile::from_csv(&format!("{}(0,0),(20,20),10\n", header)).is_err());
+
+    for (row, message) in [
+        ("east,west,10", "unknown zone `east`"),
+        ("\"(5,5)\",west,10", "no point at (5,5)"),
+        ("west,\"(20,20)\",-1", "trips_per_hour must be 0 or more"),
+    ] {
+        let file = DemandFile::from_csv(&format!("{}{}\n", header, row)).unwrap();
+        let error = file.resolve(&zones(), &network).unwrap_err();
+        assert!(error.to_string().contains(message), "{}: {}", row, error);
+    }
+}
@@ -0,0 +1,58 @@
+// determinism.rs
+use engine::helpers::analyzer::RunSummary;
+use engine::render::TerminalRenderer;
+use engine::{Simulation, SimulationBuilder};
+
+const TICKS: u64 = 300;
+
+fn simulation(seed: u64) -> Simulation {
+    SimulationBuilder::new()
+        .grid_size(4, 4)
+        .seed(seed)
+        .analyzer(RunSummary::default())
+        .build()
+        .unwrap()
+}
+
+// Vehicles and lights of the grid, to compare runs with
+fn state(simulation: &Simulation) -> String {
+    serde_json::to_string(simulation.grid()).unwrap()
+}
+
+// Several worker threads, so the vehicle tasks finish in a different
+// order from run to run
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn same_seed_gives_the_same_run_tick_for_tick() {
+    let mut first = simulation(7);
+    let mut second = simulation(7);
+    assert_eq!(state(&first), state(&second));
+    for tick in 1..=TICKS {
+        first.step().await;
+        second.step().await;
+        assert_eq!(state(&first), state(&second), "runs differ at tick {}", tick);
+    }
+    assert!(!first.grid().vehicles.is_empty());
+    assert_eq!(first.reports(), second.reports());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn other_seeds_give_other_runs() {
+    let mut first = simulation(7);
+    let mut second = simulation(8);
+    for _ in 0..TICKS {
+        first.step().await;
+        second.step().await;
+    }
+    assert_ne!(state(&first), state(&second));
+}
+
+#[tokio::test]
+async fn simulations_step_on_spawned_tasks() {
+    let simulation = SimulationBuilder::new().seed(7).renderer(TerminalRenderer).build().unwrap();
+    let simulation = tokio::spawn(async move {
+        let mut simulation = simulation;
+        simulation.step().await;
+        simulation
+    }).await.unwrap();
+    assert_eq!(simulation.tick(), 1);
+}
@@ -0,0 +1,59 @@
+// event_log.rs
+use engine::event_log::{Replay, KEYFRAME_INTERVAL};
+use engine::helpers::grid::Grid;
+use engine::SimulationBuilder;
+use std::path::PathBuf;
+
+const TICKS: u64 = 3 * KEYFRAME_INTERVAL + 50;
+
+fn temp_file(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("engine-{}-{}", std::process::id(), name))
+}
+
+// What a replay shows of the grid: where the vehicles are and the lights
+fn shown(grid: &Grid) -> String {
+    let mut vehicles: Vec<_> = grid.vehicles.iter()
+        .map(|vehicle| (
+            vehicle.id,
+            vehicle.current_position,
+            vehicle.lane,
+            vehicle.edge_progress,
+            vehicle.route_index,
+            vehicle.current_speed,
+            &vehicle.route,
+        ))
+        .collect();
+    vehicles.sort_by_key(|vehicle| vehicle.0);
+    let lights: Vec<_> = grid.traffic_lights.iter().map(|light| (light.position, &light.light_state)).collect();
+    serde_json::to_string(&(vehicles, lights)).unwrap()
+}
+
+#[tokio::test]
+async fn replays_go_back_and_forth_in_the_log() {
+    let path = temp_file("replay.jsonl");
+    let mut simulation = SimulationBuilder::new()
+        .grid_size(4, 4)
+        .seed(7)
+        .event_log(path.to_str().unwrap())
+        .build()
+        .unwrap();
+    let mut states = vec![shown(simulation.grid())];
+    for _ in 0..TICKS {
+        simulation.step().await;
+        states.push(shown(simulation.grid()));
+    }
+    drop(simulation);
+
+    let mut replay = Replay::open(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    // Forward past a few keyframes, then back before, between and onto them
+    for tick in [TICKS - 10, 20, 2 * KEYFRAME_INTERVAL + 5, KEYFRAME_INTERVAL, KEYFRAME_INTERVAL - 1, 0, TICKS] {
+        replay.seek(tick).unwrap();
+        assert_eq!(replay.tick, tick);
+        assert_eq!(shown(&replay.grid), states[tick as usize], "replay differs at tick {}", tick);
+    }
+    // Past the end stays at the end
+    replay.seek(TICKS + 100).unwrap();
+    assert_eq!(replay.tick, TICKS);
+    assert!(!replay.next_tick().unwrap());
+}
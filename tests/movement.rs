@@ -0,0 +1,218 @@
+// movement.rs
+use engine::helpers::config::{LightConfig, RoadConfig, VehicleProfiles};
+use engine::helpers::grid::Grid;
+use engine::helpers::vehicle::{Vehicle, VehicleType};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+const DT: f32 = 0.3;
+// Far more than any trip on the test grids needs
+const MAX_TICKS: usize = 2000;
+// Points are 10 units apart in generated grids
+const SPACING: i32 = 10;
+
+// Lattice of height x width points, with or without traffic lights
+fn grid(height: i32, width: i32, lights: bool) -> Grid {
+    let mut rng = ChaCha8Rng::seed_from_u64(1);
+    let mut grid = Grid::new().generate_grid(height, width, &LightConfig::default(), &RoadConfig::default(), &mut rng);
+    if !lights {
+        grid.traffic_lights.clear();
+    }
+    grid
+}
+
+fn points(grid: &Grid) -> Vec<(i32, i32)> {
+    grid.network.points.iter().map(|p| (p.x, p.y)).collect()
+}
+
+// Whether a position lies on one of the roads of a generated grid
+fn on_road(grid: &Grid, (x, y): (f32, f32)) -> bool {
+    let max_x = points(grid).iter().map(|p| p.0).max().unwrap() as f32;
+    let max_y = points(grid).iter().map(|p| p.1).max().unwrap() as f32;
+    let on_line = |value: f32| (value / SPACING as f32 - (value / SPACING as f32).round()).abs() < 1e-4;
+    let inside = (-1e-3..=max_x + 1e-3).contains(&x) && (-1e-3..=max_y + 1e-3).contains(&y);
+    inside && (on_line(x) || on_line(y))
+}
+
+fn is_point(position: (f32, f32)) -> bool {
+    position.0 as i32 % SPACING == 0 && position.1 as i32 % SPACING == 0
+        && position.0.fract() == 0.0 && position.1.fract() == 0.0
+}
+
+fn manhattan(a: (f32, f32), b: (f32, f32)) -> f32 {
+    (a.0 - b.0).abs() + (a.1 - b.1).abs()
+}
+
+// Drive a single vehicle until it leaves the grid, returning every
+// position it held (not the destination, vehicles are taken off the grid
+// in the tick they get there). Panics if it is still driving after MAX_TICKS.
+async fn drive(grid: &mut Grid, vehicle: Vehicle) -> Vec<(f32, f32)> {
+    let id = vehicle.id;
+    let mut trail = vec![vehicle.current_position];
+    assert!(grid.add_vehicle(vehicle));
+    for _ in 0..MAX_TICKS {
+        grid.update_vehicles(DT).await;
+        match grid.vehicles.iter().find(|vehicle| vehicle.id == id) {
+            Some(vehicle) => trail.push(vehicle.current_position),
+            None => return trail,
+        }
+    }
+    panic!("vehicle {} still driving after {} ticks, at {:?}", id, MAX_TICKS, trail.last());
+}
+
+// What every trip on an empty grid without lights must look like: on the
+// roads all the time, never faster than the vehicle can go, turning only
+// at points, always getting closer and in reach of the destination when
+// it arrives
+fn check_trail(grid: &Grid, trail: &[(f32, f32)], destination: (i32, i32), max_speed: f32) {
+    let target = (destination.0 as f32, destination.1 as f32);
+    for pair in trail.windows(2) {
+        let (from, to) = (pair[0], pair[1]);
+        assert!(on_road(grid, to), "{:?} is off the roads, trail {:?}", to, trail);
+        assert!(
+            manhattan(from, to) <= max_speed * DT + 1e-3,
+            "{:?} to {:?} is faster than {}, trail {:?}", from, to, max_speed, trail
+        );
+        // Both coordinates change only when the vehicle turned at a point
+        if from.0 != to.0 && from.1 != to.1 {
+            assert!(
+                is_point((to.0, from.1)) || is_point((from.0, to.1)),
+                "{:?} to {:?} cuts a corner, trail {:?}", from, to, trail
+            );
+        }
+        assert!(
+            manhattan(to, target) <= manhattan(from, target) + 1e-3,
+            "{:?} to {:?} moves away from {:?}, trail {:?}", from, to, destination, trail
+        );
+    }
+    let last = *trail.last().unwrap();
+    assert!(manhattan(last, target) <= max_speed * DT + 1e-3, "trail {:?}", trail);
+}
+
+#[tokio::test]
+async fn every_trip_between_points_arrives_on_the_roads() {
+    let profiles = VehicleProfiles::default();
+    let empty = grid(3, 4, false);
+    for start in points(&empty) {
+        for destination in points(&empty) {
+            if start == destination {
+                continue;
+            }
+            let mut grid = empty.clone();
+            let vehicle = Vehicle::with_profile(1, VehicleType::Car, start, destination, &profiles);
+            let max_speed = vehicle.max_speed;
+            let trail = drive(&mut grid, vehicle).await;
+            check_trail(&grid, &trail, destination, max_speed);
+        }
+    }
+}
+
+#[tokio::test]
+async fn trips_towards_smaller_coordinates_move_smoothly() {
+    let profiles = VehicleProfiles::default();
+    let mut grid = grid(3, 3, false);
+    let vehicle = Vehicle::with_profile(1, VehicleType::Car, (20, 20), (0, 0), &profiles);
+    let trail = drive(&mut grid, vehicle).await;
+
+    // No jump straight to the destination coordinate
+    for pair in trail.windows(2) {
+        assert!(pair[1].0 <= pair[0].0 && pair[1].1 <= pair[0].1);
+        assert!(manhattan(pair[0], pair[1]) <= 2.0 * DT + 1e-3);
+    }
+    assert!(trail.len() > 40, "arrived in {} ticks", trail.len());
+}
+
+#[tokio::test]
+async fn every_trip_from_between_points_stays_on_its_road() {
+    let profiles = VehicleProfiles::default().car;
+    let empty = grid(3, 3, false);
+    let mut starts = Vec::new();
+    for (x, y) in points(&empty) {
+        starts.push((x as f32 + 5.0, y as f32));
+        starts.push((x as f32, y as f32 + 3.0));
+    }
+    starts.retain(|&start| on_road(&empty, start));
+
+    for &start in &starts {
+        for destination in points(&empty) {
+            let mut grid = empty.clone();
+            let vehicle = Vehicle::new(1, VehicleType::Car, start, 0.0, profiles.speed, destination, profiles.priority);
+            let trail = drive(&mut grid, vehicle).await;
+            check_trail(&grid, &trail, destination, profiles.speed);
+        }
+    }
+}
+
+#[tokio::test]
+async fn vehicles_off_the_roads_give_up_without_moving() {
+    let profiles = VehicleProfiles::default().car;
+    let mut grid = grid(3, 3, false);
+    let vehicle = Vehicle::new(1, VehicleType::Car, (5.0, 5.0), 0.0, profiles.speed, (20, 20), profiles.priority);
+    let trail = drive(&mut grid, vehicle).await;
+    assert_eq!(trail, vec![(5.0, 5.0)]);
+}
+
+#[tokio::test]
+async fn trips_through_traffic_lights_stay_on_the_roads() {
+    let profiles = VehicleProfiles::default();
+    let lit = grid(3, 3, true);
+    assert!(!lit.traffic_lights.is_empty());
+    for start in points(&lit) {
+        for destination in points(&lit) {
+            if start == destination {
+                continue;
+            }
+            let mut grid = lit.clone();
+            let vehicle = Vehicle::with_profile(1, VehicleType::Bus, start, destination, &profiles);
+            let id = vehicle.id;
+            let mut trail = vec![vehicle.current_position];
+            assert!(grid.add_vehicle(vehicle));
+            for _ in 0..MAX_TICKS {
+                grid.update_traffic_lights(DT).await;
+                grid.update_vehicles(DT).await;
+                match grid.vehicles.iter().find(|vehicle| vehicle.id == id) {
+                    Some(vehicle) => trail.push(vehicle.current_position),
+                    None => break,
+                }
+            }
+            assert!(grid.vehicles.is_empty(), "{:?} to {:?} never arrived", start, destination);
+            assert!(trail.iter().all(|&position| on_road(&grid, position)), "trail {:?}", trail);
+            let last = *trail.last().unwrap();
+            assert!(manhattan(last, (destination.0 as f32, destination.1 as f32)) <= profiles.bus.speed * DT + 1e-3);
+        }
+    }
+}
+
+#[tokio::test]
+async fn rerouting_before_the_first_edge_picks_a_lane_it_has() {
+    use engine::helpers::network::RoadNetwork;
+    use engine::helpers::point::Point;
+    use std::sync::Arc;
+
+    // Two ways from (0,0) to (10,10): a two-lane road east first, or a
+    // one-lane road south first
+    let mut network = RoadNetwork::new();
+    for (x, y) in [(0, 0), (10, 0), (0, 10), (10, 10)] {
+        network.add_point(Point { x, y, is_intersection: false });
+    }
+    let east = network.add_edge(0, 1, 2, 3.0);
+    let south = network.add_edge(0, 2, 1, 3.0);
+    let east_then_south = network.add_edge(1, 3, 1, 3.0);
+    network.add_edge(2, 3, 1, 3.0);
+    let mut grid = Grid::new();
+    grid.network = Arc::new(network);
+    // The east road got slow, so the vehicle switches to the south one
+    grid.edge_delays = vec![1.0; grid.network.edges.len()];
+    grid.edge_delays[east] = 10.0;
+
+    let mut vehicle = Vehicle::with_profile(1, VehicleType::Car, (0, 0), (10, 10), &VehicleProfiles::default());
+    vehicle.route = vec![east, east_then_south];
+    vehicle.lane = 1;
+    grid.vehicles.push(vehicle);
+    grid.update_vehicles(DT).await;
+
+    let vehicle = &grid.vehicles[0];
+    assert_eq!(vehicle.route[vehicle.route_index], south);
+    assert_eq!(vehicle.lane, 0);
+    assert!(grid.check_occupancy().is_ok());
+}
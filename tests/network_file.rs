@@ -0,0 +1,70 @@
+// network_file.rs
+use engine::helpers::config::{LightConfig, RoadConfig};
+use engine::helpers::grid::Grid;
+use engine::helpers::network_file::NetworkFile;
+use engine::SimulationBuilder;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::sync::Arc;
+
+// A T junction with a light, a one-way street and a road that is wider
+// one way than the other
+const NETWORK: &str = r#"{
+  "nodes": [
+    { "id": 10, "x": 0, "y": 0 },
+    { "id": 20, "x": 10, "y": 0, "signalized": true },
+    { "id": 30, "x": 20, "y": 0 },
+    { "id": 40, "x": 10, "y": 10 }
+  ],
+  "edges": [
+    { "from": 10, "to": 20, "lanes": 2 },
+    { "from": 20, "to": 30, "speed_limit": 1.5 },
+    { "from": 40, "to": 20, "one_way": true },
+    { "from": 30, "to": 40, "lanes": 2, "one_way": true },
+    { "from": 40, "to": 30, "lanes": 1, "one_way": true }
+  ]
+}"#;
+
+fn load(json: &str) -> Grid {
+    let mut rng = ChaCha8Rng::seed_from_u64(7);
+    let (network, traffic_lights) = NetworkFile::from_json(json).unwrap()
+        .build(&LightConfig::default(), &RoadConfig::default(), &mut rng)
+        .unwrap();
+    let mut grid = Grid::new();
+    grid.network = Arc::new(network);
+    grid.traffic_lights = traffic_lights;
+    grid
+}
+
+// Points, roads and lights, whatever order the roads are listed in
+fn layout(grid: &Grid) -> String {
+    let network = &grid.network;
+    let points: Vec<_> = network.points.iter().map(|point| (point.x, point.y, point.is_intersection)).collect();
+    let mut edges: Vec<_> = network.edges.iter()
+        .map(|edge| (network.position(edge.from), network.position(edge.to), edge.lanes, edge.speed_limit, edge.length))
+        .collect();
+    edges.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let lights: Vec<_> = grid.traffic_lights.iter().map(|light| light.position).collect();
+    serde_json::to_string(&(points, edges, lights)).unwrap()
+}
+
+#[test]
+fn exported_networks_load_back_the_same() {
+    let grid = load(NETWORK);
+    assert_eq!(grid.network.edges.len(), 7);
+    let exported = NetworkFile::from_grid(&grid).to_json();
+    assert_eq!(layout(&load(&exported)), layout(&grid));
+}
+
+#[test]
+fn generated_grids_load_back_the_same() {
+    let simulation = SimulationBuilder::new()
+        .grid_size(3, 4)
+        .roads(RoadConfig { lanes: 2, speed_limit: 2.5 })
+        .seed(7)
+        .build()
+        .unwrap();
+    let grid = simulation.grid();
+    let exported = NetworkFile::from_grid(grid).to_json();
+    assert_eq!(layout(&load(&exported)), layout(grid));
+}
@@ -0,0 +1,103 @@
+// osm.rs
+use engine::helpers::config::RoadConfig;
+use engine::helpers::network::RoadNetwork;
+use engine::helpers::osm::parse_osm;
+
+// Near the equator 0.001 degrees are about 111 m both ways, 11 units at
+// 10 m per unit. Node 5 lies a few centimeters from node 2 and way 14
+// joins them, node 99 is outside the extract.
+const EXTRACT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<osm version="0.6">
+  <node id="1" lat="0.002" lon="0.000"/>
+  <node id="2" lat="0.002" lon="0.001"/>
+  <node id="3" lat="0.002" lon="0.002"/>
+  <node id="4" lat="0.001" lon="0.001"/>
+  <node id="5" lat="0.002" lon="0.0010001"/>
+  <way id="10">
+    <nd ref="1"/>
+    <nd ref="2"/>
+    <nd ref="3"/>
+    <nd ref="99"/>
+    <tag k="highway" v="primary"/>
+    <tag k="maxspeed" v="30 mph"/>
+    <tag k="lanes" v="4"/>
+  </way>
+  <way id="11">
+    <nd ref="4"/>
+    <nd ref="5"/>
+    <tag k="highway" v="residential"/>
+    <tag k="oneway" v="-1"/>
+    <tag k="maxspeed" v="50"/>
+    <tag k="lanes" v="2"/>
+  </way>
+  <way id="12">
+    <nd ref="98"/>
+    <nd ref="99"/>
+    <tag k="highway" v="residential"/>
+  </way>
+  <way id="14">
+    <nd ref="5"/>
+    <nd ref="2"/>
+    <tag k="highway" v="service"/>
+  </way>
+  <way id="13">
+    <nd ref="1"/>
+    <nd ref="4"/>
+    <tag k="highway" v="footway"/>
+  </way>
+</osm>"#;
+
+const METERS_PER_UNIT: f32 = 10.0;
+
+fn network() -> RoadNetwork {
+    parse_osm(EXTRACT, &RoadConfig::default(), METERS_PER_UNIT).unwrap().0
+}
+
+fn road(network: &RoadNetwork, from: (i32, i32), to: (i32, i32)) -> Option<usize> {
+    network.edge_between(network.point_at(from)?, network.point_at(to)?)
+}
+
+#[test]
+fn junctions_closer_than_a_unit_become_one_point() {
+    let network = network();
+    let mut positions: Vec<(i32, i32)> = network.points.iter().map(|point| (point.x, point.y)).collect();
+    positions.sort_unstable();
+    assert_eq!(positions, [(0, 0), (11, 0), (11, 11), (22, 0)]);
+}
+
+#[test]
+fn two_way_roads_split_their_lanes_per_direction() {
+    let network = network();
+    for (from, to) in [((0, 0), (11, 0)), ((11, 0), (0, 0)), ((11, 0), (22, 0)), ((22, 0), (11, 0))] {
+        let edge = road(&network, from, to).unwrap_or_else(|| panic!("no edge from {:?} to {:?}", from, to));
+        assert_eq!(network.edges[edge].lanes, 2);
+    }
+}
+
+#[test]
+fn maxspeed_in_mph_is_converted() {
+    let network = network();
+    let edge = road(&network, (0, 0), (11, 0)).unwrap();
+    // 30 mph = 13.41 m/s
+    assert!((network.edges[edge].speed_limit - 13.4112 / METERS_PER_UNIT).abs() < 1e-3);
+    let edge = road(&network, (11, 0), (11, 11)).unwrap();
+    assert!((network.edges[edge].speed_limit - 50.0 / 3.6 / METERS_PER_UNIT).abs() < 1e-3);
+}
+
+#[test]
+fn reverse_one_way_roads_run_against_the_node_order() {
+    let network = network();
+    let edge = road(&network, (11, 0), (11, 11)).unwrap();
+    // One-way roads keep every lane of the tag
+    assert_eq!(network.edges[edge].lanes, 2);
+    assert!(road(&network, (11, 11), (11, 0)).is_none());
+}
+
+#[test]
+fn ways_clipped_by_the_extract_keep_the_part_inside() {
+    let network = network();
+    // Way 10 ends at node 3 inside the extract, way 12 is left out,
+    // way 14 shrank to nothing and footways are not roads
+    assert!(road(&network, (11, 0), (22, 0)).is_some());
+    assert_eq!(network.edges.len(), 5);
+}
@@ -0,0 +1,93 @@
+// snapshot.rs
+use engine::helpers::analyzer::{Analyzer, RunSummary};
+use engine::helpers::message::SimulationMessage;
+use engine::snapshot::Snapshot;
+use engine::{Simulation, SimulationBuilder};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+const SEED: u64 = 7;
+// Snapshot taken after this many ticks, the runs end after twice as many
+const HALF: u64 = 100;
+
+fn builder() -> SimulationBuilder {
+    SimulationBuilder::new().grid_size(4, 4).seed(SEED)
+}
+
+fn temp_file(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("engine-{}-{}", std::process::id(), name))
+}
+
+// Everything a snapshot keeps, the random number stream included
+fn state(simulation: &Simulation) -> String {
+    serde_json::to_string(&Snapshot::capture(simulation)).unwrap()
+}
+
+// Average speed of every tick after the first half
+#[derive(Clone, Default)]
+struct SecondHalfSpeeds(Arc<Mutex<Vec<f32>>>);
+
+impl Analyzer for SecondHalfSpeeds {
+    fn record(&mut self, message: &SimulationMessage) {
+        if let SimulationMessage::GridUpdate { tick, average_speed, .. } = message {
+            if *tick > HALF {
+                self.0.lock().unwrap().push(*average_speed);
+            }
+        }
+    }
+}
+
+async fn straight_run(speeds: SecondHalfSpeeds) -> Simulation {
+    let mut simulation = builder().analyzer(speeds).build().unwrap();
+    for _ in 0..2 * HALF {
+        simulation.step().await;
+    }
+    simulation
+}
+
+// Run the first half, save it, resume from the file and run the second half
+async fn resumed_run(name: &str) -> Simulation {
+    let path = temp_file(name);
+    let mut first = builder().build().unwrap();
+    for _ in 0..HALF {
+        first.step().await;
+    }
+    first.save_snapshot(&path).unwrap();
+
+    let mut second = builder()
+        .resume_from(path.to_str().unwrap())
+        .analyzer(RunSummary::default())
+        .build()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(second.tick(), HALF);
+    for _ in 0..HALF {
+        second.step().await;
+    }
+    second
+}
+
+#[tokio::test]
+async fn resumed_runs_continue_exactly() {
+    let straight = straight_run(SecondHalfSpeeds::default()).await;
+    assert!(!straight.grid().vehicles.is_empty());
+    for name in ["resume.json", "resume.snapshot"] {
+        let resumed = resumed_run(name).await;
+        assert_eq!(state(&straight), state(&resumed), "{} resumed differently", name);
+    }
+}
+
+#[tokio::test]
+async fn resumed_runs_average_their_own_ticks() {
+    let speeds = SecondHalfSpeeds::default();
+    straight_run(speeds.clone()).await;
+    let speeds = speeds.0.lock().unwrap().clone();
+    let expected = speeds.iter().sum::<f32>() / speeds.len() as f32;
+
+    let resumed = resumed_run("average.snapshot").await;
+    let report = resumed.reports().remove(0);
+    assert!(
+        report.contains(&format!("Average speed:    {:.2} units/s", expected)),
+        "expected an average speed of {:.2}, got\n{}", expected, report
+    );
+}